@@ -0,0 +1,59 @@
+use std::{str::FromStr, time::Instant};
+
+use crate::common::{board::Board, r#move::Move};
+
+const PERFT_USAGE: &str = "usage: ace-chess perft <depth> [fen]";
+
+/// Run `perft` from the command line, printing the node count of each first move.
+///
+/// Without a FEN the initial position is used.
+pub fn perft(args: &[String]) -> Result<(), String> {
+    let (depth, fen) = args.split_first().ok_or(PERFT_USAGE)?;
+    let depth: u32 = depth.parse().map_err(|_| PERFT_USAGE)?;
+    // The FEN may be given quoted or not
+    let mut fen = fen.join(" ");
+    let board = match fen.split_whitespace().count() {
+        0 => Board::default(),
+        len => {
+            // Move counters are often left out of test positions
+            if len == 4 {
+                fen.push_str(" 0 1");
+            }
+            Board::from_str(&fen).map_err(|_| format!("Invalid FEN: {fen}"))?
+        }
+    };
+
+    let start = Instant::now();
+    let (divide, nodes) = count(&board, depth);
+    for (m, nodes) in &divide {
+        println!("{m}: {nodes}");
+    }
+    println!();
+    println!("Nodes searched: {nodes}");
+    println!("Time: {:?}", start.elapsed());
+    Ok(())
+}
+
+/// The node count of each first move, and of the whole tree.
+fn count(board: &Board, depth: u32) -> (Vec<(Move, u64)>, u64) {
+    let divide = board.divide(depth);
+    // At depth 0 the position itself is the only node
+    let nodes = match depth {
+        0 => board.perft(0),
+        _ => divide.iter().map(|(_, nodes)| nodes).sum(),
+    };
+    (divide, nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_nodes() {
+        let board = Board::default();
+        assert_eq!(count(&board, 0), (vec![], 1));
+        let (divide, nodes) = count(&board, 2);
+        assert_eq!((divide.len(), nodes), (20, 400));
+    }
+}
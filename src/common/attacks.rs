@@ -0,0 +1,194 @@
+//! Precomputed attack tables.
+//!
+//! Leapers (pawns, knights and kings) are a single lookup. Sliders use the classical
+//! approach: the ray in each direction is cut at the first blocker, found with a bit scan.
+
+use std::sync::OnceLock;
+
+use super::bitboard::{BitBoard, EMPTY};
+use super::color::Color;
+use super::direction::{Direction, ALL_DIAGONAL, ALL_DIRECTION, ALL_LINE, NUM_DIRECTION};
+use super::square::{Square, ALL_SQUARES, NUM_SQUARES};
+use super::NUM_COLORS;
+
+struct Tables {
+    pawn: [[BitBoard; NUM_SQUARES]; NUM_COLORS],
+    knight: [BitBoard; NUM_SQUARES],
+    king: [BitBoard; NUM_SQUARES],
+    rays: [[BitBoard; NUM_SQUARES]; NUM_DIRECTION],
+}
+
+/// (file, rank) step of a [`Direction`].
+fn step(direction: Direction) -> (i8, i8) {
+    match direction {
+        Direction::Up => (0, 1),
+        Direction::UpRight => (1, 1),
+        Direction::Right => (1, 0),
+        Direction::DownRight => (1, -1),
+        Direction::Down => (0, -1),
+        Direction::DownLeft => (-1, -1),
+        Direction::Left => (-1, 0),
+        Direction::UpLeft => (-1, 1),
+    }
+}
+
+/// Whether the squares of a ray get higher (so the nearest blocker is the lowest bit).
+fn is_increasing(direction: Direction) -> bool {
+    let (file, rank) = step(direction);
+    rank > 0 || (rank == 0 && file > 0)
+}
+
+/// The [`Square`] at the given offset, if it is on the board.
+fn offset(square: Square, (file, rank): (i8, i8)) -> Option<Square> {
+    let file = square.file().as_index() as i8 + file;
+    let rank = square.rank().as_index() as i8 + rank;
+    ((0..8).contains(&file) && (0..8).contains(&rank))
+        .then(|| Square::new((rank * 8 + file) as usize))
+}
+
+fn leaper(square: Square, offsets: &[(i8, i8)]) -> BitBoard {
+    offsets
+        .iter()
+        .filter_map(|o| offset(square, *o))
+        .fold(EMPTY, |bitboard, s| bitboard | BitBoard::from_square(s))
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut tables = Tables {
+            pawn: [[EMPTY; NUM_SQUARES]; NUM_COLORS],
+            knight: [EMPTY; NUM_SQUARES],
+            king: [EMPTY; NUM_SQUARES],
+            rays: [[EMPTY; NUM_SQUARES]; NUM_DIRECTION],
+        };
+        for square in ALL_SQUARES {
+            let i = square.as_index();
+            tables.pawn[Color::White.as_index()][i] = leaper(square, &[(-1, 1), (1, 1)]);
+            tables.pawn[Color::Black.as_index()][i] = leaper(square, &[(-1, -1), (1, -1)]);
+            tables.knight[i] = leaper(
+                square,
+                &[
+                    (1, 2),
+                    (2, 1),
+                    (2, -1),
+                    (1, -2),
+                    (-1, -2),
+                    (-2, -1),
+                    (-2, 1),
+                    (-1, 2),
+                ],
+            );
+            tables.king[i] = ALL_DIRECTION
+                .iter()
+                .fold(EMPTY, |bitboard, d| bitboard | leaper(square, &[step(*d)]));
+            for (d, direction) in ALL_DIRECTION.iter().enumerate() {
+                let mut current = square;
+                while let Some(next) = offset(current, step(*direction)) {
+                    tables.rays[d][i] |= BitBoard::from_square(next);
+                    current = next;
+                }
+            }
+        }
+        tables
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+/// Squares attacked by a pawn of the given [`Color`].
+pub fn pawn_attacks(color: Color, square: Square) -> BitBoard {
+    tables().pawn[color.as_index()][square.as_index()]
+}
+
+/// Squares attacked by a knight.
+pub fn knight_attacks(square: Square) -> BitBoard {
+    tables().knight[square.as_index()]
+}
+
+/// Squares attacked by a king.
+pub fn king_attacks(square: Square) -> BitBoard {
+    tables().king[square.as_index()]
+}
+
+/// Squares seen from `square` in each direction, up to and including the first occupied one.
+fn slider_attacks(square: Square, occupied: BitBoard, directions: &[Direction]) -> BitBoard {
+    let tables = tables();
+    directions.iter().fold(EMPTY, |attacks, direction| {
+        // Directions are laid out in the order of `ALL_DIRECTION`.
+        let d = *direction as usize;
+        let ray = tables.rays[d][square.as_index()];
+        let blockers = ray & occupied;
+        let blocker = if is_increasing(*direction) {
+            blockers.first()
+        } else {
+            blockers.last()
+        };
+        match blocker {
+            Some(blocker) => attacks | (ray ^ tables.rays[d][blocker.as_index()]),
+            None => attacks | ray,
+        }
+    })
+}
+
+/// Squares attacked by a bishop, given the occupied squares.
+pub fn bishop_attacks(square: Square, occupied: BitBoard) -> BitBoard {
+    slider_attacks(square, occupied, &ALL_DIAGONAL)
+}
+
+/// Squares attacked by a rook, given the occupied squares.
+pub fn rook_attacks(square: Square, occupied: BitBoard) -> BitBoard {
+    slider_attacks(square, occupied, &ALL_LINE)
+}
+
+/// Squares attacked by a queen, given the occupied squares.
+pub fn queen_attacks(square: Square, occupied: BitBoard) -> BitBoard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(bitboard: BitBoard) -> Vec<Square> {
+        bitboard.collect()
+    }
+
+    #[test]
+    fn leapers() {
+        assert_eq!(
+            squares(knight_attacks(Square::A1)),
+            [Square::C2, Square::B3]
+        );
+        assert_eq!(knight_attacks(Square::E4).count(), 8);
+        assert_eq!(king_attacks(Square::H8).count(), 3);
+        assert_eq!(
+            squares(pawn_attacks(Color::White, Square::A2)),
+            [Square::B3]
+        );
+        assert_eq!(
+            squares(pawn_attacks(Color::Black, Square::E4)),
+            [Square::D3, Square::F3]
+        );
+    }
+
+    #[test]
+    fn sliders_stop_at_blockers() {
+        let occupied = BitBoard::from_square(Square::D6) | BitBoard::from_square(Square::F4);
+        assert!(!rook_attacks(Square::D4, occupied).contains(Square::D7));
+        assert!(rook_attacks(Square::D4, occupied).contains(Square::D6));
+        assert!(rook_attacks(Square::D4, occupied).contains(Square::F4));
+        assert!(!rook_attacks(Square::D4, occupied).contains(Square::G4));
+        assert_eq!(rook_attacks(Square::D4, EMPTY).count(), 14);
+        assert_eq!(bishop_attacks(Square::D4, EMPTY).count(), 13);
+        assert_eq!(
+            squares(bishop_attacks(
+                Square::A1,
+                BitBoard::from_square(Square::C3)
+            )),
+            [Square::B2, Square::C3]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Sets of squares packed in a `u64`.
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use super::square::Square;
+
+/// A set of [`Square`], one bit per square (A1 is the lowest bit, H8 the highest).
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct BitBoard(pub u64);
+
+/// The [`BitBoard`] without any square.
+pub const EMPTY: BitBoard = BitBoard(0);
+
+impl BitBoard {
+    /// Create a [`BitBoard`] containing only the given [`Square`].
+    #[inline]
+    pub fn from_square(square: Square) -> Self {
+        BitBoard(1 << square.as_index())
+    }
+
+    /// Verify if the [`Square`] is in the set.
+    #[inline]
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << square.as_index()) != 0
+    }
+
+    /// Verify if the set has no square.
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of squares in the set.
+    #[inline]
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The lowest [`Square`] of the set (closest to A1).
+    #[inline]
+    pub fn first(self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::new(self.0.trailing_zeros() as usize))
+    }
+
+    /// The highest [`Square`] of the set (closest to H8).
+    #[inline]
+    pub fn last(self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::new(63 - self.0.leading_zeros() as usize))
+    }
+}
+
+/// Iterate over the squares of the set, from A1 to H8.
+impl Iterator for BitBoard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let square = self.first()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+impl From<Square> for BitBoard {
+    fn from(square: Square) -> Self {
+        BitBoard::from_square(square)
+    }
+}
+
+impl BitAnd for BitBoard {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        BitBoard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for BitBoard {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        BitBoard(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for BitBoard {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        BitBoard(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for BitBoard {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        BitBoard(!self.0)
+    }
+}
+
+impl BitAndAssign for BitBoard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for BitBoard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for BitBoard {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iterates_squares_in_order() {
+        let bitboard = BitBoard::from_square(Square::H8)
+            | BitBoard::from_square(Square::A1)
+            | BitBoard::from_square(Square::E4);
+
+        assert_eq!(bitboard.count(), 3);
+        assert_eq!(bitboard.first(), Some(Square::A1));
+        assert_eq!(bitboard.last(), Some(Square::H8));
+        assert_eq!(
+            bitboard.collect::<Vec<_>>(),
+            vec![Square::A1, Square::E4, Square::H8]
+        );
+        assert_eq!(EMPTY.first(), None);
+    }
+}
@@ -8,19 +8,21 @@ use crate::common::rank::ALL_RANKS;
 use crate::common::square::ALL_SQUARES;
 use crate::error::Error;
 use std::fmt;
-use std::ops::{Index, IndexMut};
+use std::ops::Index;
 use std::str::FromStr;
 
 use crate::{Result, Square};
 
+use super::attacks::{
+    bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+};
+use super::bitboard::{BitBoard, EMPTY};
 use super::color::Color;
 use super::direction::Direction;
-use super::direction::ALL_DIAGONAL;
-use super::direction::ALL_DIRECTION;
-use super::direction::ALL_LINE;
-use super::piece::Piece;
+use super::piece::{Piece, NUM_PIECES, PROMOTION_PIECES};
 use super::r#move::Move;
 use super::square::NUM_SQUARES;
+use super::status::GameStatus;
 use super::NUM_COLORS;
 
 /// A representation of a chess board that implement FEN notation ([`Board::from_str`]).
@@ -64,6 +66,8 @@ use super::NUM_COLORS;
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct Board {
     squares: [Option<(Piece, Color)>; NUM_SQUARES],
+    pieces: [BitBoard; NUM_PIECES],
+    colors: [BitBoard; NUM_COLORS],
     pub side_to_move: Color,
     pub castle_rights: [CastleRights; NUM_COLORS],
     pub en_passant: Option<Square>,
@@ -78,6 +82,8 @@ impl Board {
     pub fn new() -> Self {
         Board {
             squares: [None; NUM_SQUARES],
+            pieces: [EMPTY; NUM_PIECES],
+            colors: [EMPTY; NUM_COLORS],
             side_to_move: Color::White,
             castle_rights: [CastleRights::NoRights; NUM_COLORS],
             en_passant: None,
@@ -112,16 +118,29 @@ impl Board {
     }
 
     /// Check if the [`Move`] is legal.
+    ///
+    /// A promotion piece is only allowed on a pawn move to the last rank.
     pub fn is_legal(&self, m: Move) -> bool {
         let mut is_legal = false;
         if let Some(side) = self.color_on(m.from) {
             if side == self.side_to_move && self.get_legal_moves(m.from).contains(&m.to) {
-                is_legal = true;
+                is_legal = match m.promotion {
+                    None => true,
+                    Some(piece) => self.is_promotion(m) && PROMOTION_PIECES.contains(&piece),
+                };
             }
         }
         is_legal
     }
 
+    /// Check if the [`Move`] takes a pawn to the last rank.
+    pub fn is_promotion(&self, m: Move) -> bool {
+        match self.on(m.from) {
+            Some((Piece::Pawn, side)) => m.to.rank_for(side) == Rank::Eighth,
+            _ => false,
+        }
+    }
+
     /// Update the chessboard according to the chess rules.
     ///
     /// Assume that the [`Move`] is legal.
@@ -146,14 +165,11 @@ impl Board {
         match piece_from {
             // Pawn: En Passant, promotion
             Piece::Pawn => {
-                self[cmove.from] = None;
-                self[cmove.to] = Some((Piece::Pawn, side));
+                self.set(cmove.from, None);
+                self.set(cmove.to, Some((Piece::Pawn, side)));
                 // if En Passant: capture the pawn
                 if self.en_passant == Some(cmove.to) {
-                    match side {
-                        Color::White => self[cmove.to.down()] = None,
-                        Color::Black => self[cmove.to.up()] = None,
-                    }
+                    self.set(cmove.to.backward(side), None);
                 }
                 // Set self.en_passant
                 if cmove.distance() == 2 {
@@ -168,37 +184,26 @@ impl Board {
                 }
 
                 // Promotion
-                // TODO: Give user option on what piece to promote to
                 if cmove.to.rank_for(side) == Rank::Eighth {
-                    self[cmove.to] = Some((Piece::Queen, side));
+                    self.set(
+                        cmove.to,
+                        Some((cmove.promotion.unwrap_or(Piece::Queen), side)),
+                    );
                 }
             }
             // King: Castle
             Piece::King => {
+                self.set(cmove.from, None);
+                self.set(cmove.to, Some((Piece::King, side)));
+                // A king moving two squares is castling, the rook goes to the other side
                 if cmove.from.distance(cmove.to) == 2 {
-                    if self.castle_rights(side).has_kingside()
-                        && cmove.from.file() < cmove.to.file()
-                    {
-                        // if is a Castle - Kingside
-                        self[cmove.from] = None;
-                        self[cmove.to] = Some((Piece::King, side));
-                        self[cmove.to.right()] = None;
-                        self[cmove.to.left()] = Some((Piece::Rook, side));
-                    } else if self.castle_rights(side).has_queenside()
-                        && cmove.to.file() < cmove.from.file()
-                    {
-                        // if is a Castle - Queenside
-                        self[cmove.from] = None;
-                        self[cmove.to] = Some((Piece::King, side));
-                        self[cmove.to.left().left()] = None;
-                        self[cmove.to.right()] = Some((Piece::Rook, side));
+                    let (rook_from, rook_to) = if cmove.from.file() < cmove.to.file() {
+                        (cmove.to.right(), cmove.to.left())
                     } else {
-                        panic!("Error::InvalidMove: Board: {self}, invalid_move: {cmove}");
-                    }
-                } else {
-                    // normal move
-                    self[cmove.from] = None;
-                    self[cmove.to] = Some((Piece::King, side));
+                        (cmove.to.left().left(), cmove.to.right())
+                    };
+                    self.set(rook_from, None);
+                    self.set(rook_to, Some((Piece::Rook, side)));
                 }
 
                 // If the king move he lost both CastleRights
@@ -207,21 +212,28 @@ impl Board {
             // Rook: Castle
             Piece::Rook => {
                 // remove CastleRights
-                match cmove.from {
-                    Square::A1 | Square::A8 => {
-                        self.remove_castle_rights(side, CastleRights::QueenSide);
+                if cmove.from.rank_for(side) == Rank::First {
+                    match cmove.from.file() {
+                        File::A => self.remove_castle_rights(side, CastleRights::QueenSide),
+                        File::H => self.remove_castle_rights(side, CastleRights::KingSide),
+                        _ => {}
                     }
-                    Square::H1 | Square::H8 => {
-                        self.remove_castle_rights(side, CastleRights::KingSide);
-                    }
-                    _ => {}
                 }
-                self[cmove.from] = None;
-                self[cmove.to] = Some((Piece::Rook, side));
+                self.set(cmove.from, None);
+                self.set(cmove.to, Some((Piece::Rook, side)));
             }
             _ => {
-                self[cmove.from] = None;
-                self[cmove.to] = Some((piece_from, side));
+                self.set(cmove.from, None);
+                self.set(cmove.to, Some((piece_from, side)));
+            }
+        }
+
+        // Taking a rook on its initial square also removes the CastleRights on its side
+        if cmove.to.rank_for(!side) == Rank::First {
+            match cmove.to.file() {
+                File::A => self.remove_castle_rights(!side, CastleRights::QueenSide),
+                File::H => self.remove_castle_rights(!side, CastleRights::KingSide),
+                _ => {}
             }
         }
 
@@ -240,15 +252,13 @@ impl Board {
     }
 
     pub fn get_valid_moves_to(&self, dest: Square, piece: Piece) -> Vec<Square> {
-        let mut valid_moves = Vec::new();
-        for square in ALL_SQUARES {
-            if self.get_legal_moves(square).contains(&dest)
-                && self.on_is(square, (piece, self.side_to_move))
-            {
-                valid_moves.push(square);
-            }
-        }
-
+        let mut valid_moves: Vec<Square> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.to == dest && self.piece_on_is(m.from, piece))
+            .map(|m| m.from)
+            .collect();
+        valid_moves.dedup();
         valid_moves
     }
     /// Remove [`CastleRights`] for a particular side.
@@ -281,6 +291,40 @@ impl Board {
         self.squares[square.as_index()].map(|(piece, _)| piece)
     }
 
+    /// Put a [`Piece`] on a [`Square`], or empty it with `None`.
+    pub fn set(&mut self, square: Square, value: Option<(Piece, Color)>) {
+        let bitboard = BitBoard::from_square(square);
+        if let Some((piece, color)) = self.squares[square.as_index()] {
+            self.pieces[piece.as_index()] ^= bitboard;
+            self.colors[color.as_index()] ^= bitboard;
+        }
+        if let Some((piece, color)) = value {
+            self.pieces[piece.as_index()] |= bitboard;
+            self.colors[color.as_index()] |= bitboard;
+        }
+        self.squares[square.as_index()] = value;
+    }
+
+    /// Get the [`BitBoard`] of a [`Piece`] for both sides.
+    pub fn pieces(&self, piece: Piece) -> BitBoard {
+        self.pieces[piece.as_index()]
+    }
+
+    /// Get the [`BitBoard`] of all the pieces of a [`Color`].
+    pub fn color_combined(&self, color: Color) -> BitBoard {
+        self.colors[color.as_index()]
+    }
+
+    /// Get the [`BitBoard`] of a [`Piece`] of a given [`Color`].
+    pub fn pieces_of(&self, piece: Piece, color: Color) -> BitBoard {
+        self.pieces(piece) & self.color_combined(color)
+    }
+
+    /// Get the [`BitBoard`] of all the occupied squares.
+    pub fn occupied(&self) -> BitBoard {
+        self.colors[Color::White.as_index()] | self.colors[Color::Black.as_index()]
+    }
+
     /// Verify if the [`Square`] is occupied by the given [`Piece`].
     pub fn piece_on_is(&self, square: Square, piece: Piece) -> bool {
         matches!(self.piece_on(square), Some(real_piece) if real_piece == piece)
@@ -331,12 +375,9 @@ impl Board {
 
     /// Get the [`Square`] of the [`Piece::King`] of the given [`Color`].
     pub fn king_of(&self, color: Color) -> Square {
-        for square in ALL_SQUARES {
-            if self.on_is(square, (Piece::King, color)) {
-                return square;
-            }
-        }
-        panic!("King square of {color:?} not found")
+        self.pieces_of(Piece::King, color)
+            .first()
+            .unwrap_or_else(|| panic!("King square of {color:?} not found"))
     }
 
     /// Verify if the [`Square`] is empty (i.e. not occupied).
@@ -356,39 +397,90 @@ impl Board {
         self.is_targeted(king_square, enemy_color)
     }
 
-    // TODO: Does this work?
+    /// Verify if the side to move is checkmated.
     pub fn is_checkmate(&self) -> bool {
-        self.is_check() && self.has_any_move()
+        self.is_check() && !self.has_any_move()
     }
 
-    /// Verify if a move expose the king (used for legality).
+    /// Verify if the side to move has no legal move while not in check.
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && !self.has_any_move()
+    }
+
+    /// Verify if neither side has enough material left to checkmate.
     ///
-    /// FIXME: A player should move if the only threat can be killed
+    /// That is a lone king against a king and at most one minor piece, or bishops all on
+    /// squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = vec![];
+        for square in ALL_SQUARES {
+            match self.piece_on(square) {
+                None | Some(Piece::King) => {}
+                Some(Piece::Knight) | Some(Piece::Bishop) => minors.push(square),
+                Some(Piece::Pawn) | Some(Piece::Rook) | Some(Piece::Queen) => return false,
+            }
+        }
+
+        let square_color =
+            |square: &Square| (square.file().as_index() + square.rank().as_index()) % 2;
+        match minors.as_slice() {
+            [] | [_] => true,
+            [first, rest @ ..] => rest.iter().chain([first]).all(|square| {
+                self.piece_on_is(*square, Piece::Bishop)
+                    && square_color(square) == square_color(first)
+            }),
+        }
+    }
+
+    /// Get the [`GameStatus`] of the position.
+    ///
+    /// > **Note**: A single position can't tell about repetitions, so this never returns
+    /// > [`GameStatus::ThreefoldRepetition`].
+    pub fn status(&self) -> GameStatus {
+        if !self.has_any_move() {
+            if self.is_check() {
+                GameStatus::Checkmate(!self.side_to_move)
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.is_insufficient_material() {
+            GameStatus::InsufficientMaterial
+        } else if self.halfmoves >= 100 {
+            GameStatus::FiftyMoves
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// Verify if a move expose the king (used for legality).
     fn is_exposing_move(&self, m: Move) -> bool {
-        let mut next_board = *self;
         let side = self.side_to_move;
-        let enemy_side = !side;
+        let mut next_board = *self;
         next_board.update(m);
-        if next_board.is_targeted(next_board.king_of(side), enemy_side) {
-            return true;
+        match next_board.pieces_of(Piece::King, side).first() {
+            Some(king_square) => next_board.is_targeted(king_square, !side),
+            None => false,
         }
-        false
     }
 
-    /// Verify if a [`Square`] can be taken by the given [`Color`] in the current [`Board`].
+    /// Verify if a [`Square`] is attacked by the given [`Color`] in the current [`Board`].
     ///
     /// > **Reciprocal**: see [`Board::is_not_targeted`].
     pub fn is_targeted(&self, target: Square, attacker: Color) -> bool {
-        let mut is_targeted = false;
-        for from_square in ALL_SQUARES {
-            if self.color_on_is(from_square, attacker)
-                && self.get_valid_moves(from_square).contains(&target)
-            {
-                is_targeted = true;
-                break;
-            }
-        }
-        is_targeted
+        !self.attackers(target, attacker).is_empty()
+    }
+
+    /// Get the pieces of the given [`Color`] attacking a [`Square`].
+    pub fn attackers(&self, target: Square, attacker: Color) -> BitBoard {
+        let occupied = self.occupied();
+        let diagonal = self.pieces(Piece::Bishop) | self.pieces(Piece::Queen);
+        let line = self.pieces(Piece::Rook) | self.pieces(Piece::Queen);
+        let attackers = (pawn_attacks(!attacker, target) & self.pieces(Piece::Pawn))
+            | (knight_attacks(target) & self.pieces(Piece::Knight))
+            | (king_attacks(target) & self.pieces(Piece::King))
+            | (bishop_attacks(target, occupied) & diagonal)
+            | (rook_attacks(target, occupied) & line);
+        attackers & self.color_combined(attacker)
     }
 
     /// Verify if a [`Square`] cannot be taken by the given [`Color`] in the current [`Board`].
@@ -419,13 +511,8 @@ impl Board {
     ///
     /// If no [`Piece`] exist on the [`Square`], then return false.
     pub fn has_any_move(&self) -> bool {
-        for from_square in ALL_SQUARES {
-            if self.color_on_is(from_square, self.side_to_move) && self.has_legal_move(from_square)
-            {
-                return true;
-            }
-        }
-        false
+        self.color_combined(self.side_to_move)
+            .any(|from| self.has_legal_move(from))
     }
 
     /// Compute and return all the valid moves for a [`Piece`] (if exist) at a given [`Square`].
@@ -434,380 +521,142 @@ impl Board {
     ///
     /// > **Note**: The legality is not verify, if you want to: use [`get_legal_moves`][Board::get_legal_moves].
     pub fn get_valid_moves(&self, from: Square) -> Vec<Square> {
-        let mut valid_moves = Vec::new();
-        if let Some((piece_from, side)) = self.on(from) {
-            let mut dest_square;
-            match piece_from {
-                Piece::Pawn => {
-                    // If square forward is is empty
-                    dest_square = from.forward(side);
-                    if self.is_empty(dest_square) {
-                        valid_moves.push(dest_square);
-
-                        // First move of the pawn
-                        dest_square = dest_square.forward(side);
-                        if from.rank_for(side) == Rank::Second && self.is_empty(dest_square) {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-
-                    // If can capture (normal or en passant)
-                    if from.file_for(side) == File::A {
-                        dest_square = from.forward(side).right();
-                        if self.color_on_is(dest_square, !side)
-                            || Some(dest_square) == self.en_passant
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    } else if from.file_for(side) == File::H {
-                        dest_square = from.forward(side).left();
-                        if self.color_on_is(dest_square, !side)
-                            || Some(dest_square) == self.en_passant
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    } else {
-                        dest_square = from.forward(side).right();
-                        if self.color_on_is(dest_square, !side)
-                            || Some(dest_square) == self.en_passant
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                        dest_square = from.forward(side).left();
-                        if self.color_on_is(dest_square, !side)
-                            || Some(dest_square) == self.en_passant
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-                Piece::Knight => {
-                    let _knight_moves = vec![
-                        from.up().up().left(),
-                        from.up().up().right(),
-                        from.right().right().up(),
-                        from.right().right().down(),
-                        from.down().down().right(),
-                        from.down().down().left(),
-                        from.left().left().down(),
-                        from.left().left().up(),
-                    ];
-                    let mut knight_moves = Vec::new();
-                    // filter
-                    for dest_square in _knight_moves {
-                        if from.distance(dest_square) == 2 {
-                            knight_moves.push(dest_square);
-                        }
-                    }
-                    // Verify legality
-                    for dest_square in knight_moves {
-                        if !self.color_on_is(dest_square, side) {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-                Piece::Bishop => {
-                    for direction in ALL_DIAGONAL {
-                        match from.rank() {
-                            Rank::First if direction.has(Direction::Down) => continue,
-                            Rank::Eighth if direction.has(Direction::Up) => continue,
-                            _ => {}
-                        }
-                        match from.file() {
-                            File::A if direction.has(Direction::Left) => continue,
-                            File::H if direction.has(Direction::Right) => continue,
-                            _ => {}
-                        }
-                        let mut old_square = from;
-                        dest_square = old_square.follow_direction(direction);
-                        while self.is_empty(dest_square) && old_square.distance(dest_square) == 1 {
-                            valid_moves.push(dest_square);
-                            old_square = dest_square;
-                            dest_square = dest_square.follow_direction(direction);
-                        }
-                        if self.color_on_is(dest_square, !side)
-                            && old_square.distance(dest_square) == 1
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-                Piece::Rook => {
-                    for direction in ALL_LINE {
-                        match from.rank() {
-                            Rank::First if direction.has(Direction::Down) => continue,
-                            Rank::Eighth if direction.has(Direction::Up) => continue,
-                            _ => {}
-                        }
-                        match from.file() {
-                            File::A if direction.has(Direction::Left) => continue,
-                            File::H if direction.has(Direction::Right) => continue,
-                            _ => {}
-                        }
-                        let mut old_square = from;
-                        dest_square = old_square.follow_direction(direction);
-                        while self.is_empty(dest_square) && old_square.distance(dest_square) == 1 {
-                            valid_moves.push(dest_square);
-                            old_square = dest_square;
-                            dest_square = dest_square.follow_direction(direction);
-                        }
-                        if self.color_on_is(dest_square, !side)
-                            && old_square.distance(dest_square) == 1
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-                Piece::Queen => {
-                    for direction in ALL_DIRECTION {
-                        match from.rank() {
-                            Rank::First if direction.has(Direction::Down) => continue,
-                            Rank::Eighth if direction.has(Direction::Up) => continue,
-                            _ => {}
-                        }
-                        match from.file() {
-                            File::A if direction.has(Direction::Left) => continue,
-                            File::H if direction.has(Direction::Right) => continue,
-                            _ => {}
-                        }
-                        let mut old_square = from;
-                        dest_square = old_square.follow_direction(direction);
-                        while self.is_empty(dest_square) && old_square.distance(dest_square) == 1 {
-                            valid_moves.push(dest_square);
-                            old_square = dest_square;
-                            dest_square = dest_square.follow_direction(direction);
-                        }
-                        if self.color_on_is(dest_square, !side)
-                            && old_square.distance(dest_square) == 1
-                        {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-                Piece::King => {
-                    for direction in ALL_DIRECTION {
-                        match from.rank() {
-                            Rank::First if direction.has(Direction::Down) => continue,
-                            Rank::Eighth if direction.has(Direction::Up) => continue,
-                            _ => {}
-                        }
-                        match from.file() {
-                            File::A if direction.has(Direction::Left) => continue,
-                            File::H if direction.has(Direction::Right) => continue,
-                            _ => {}
-                        }
-                        dest_square = from.follow_direction(direction);
-                        if !self.color_on_is(dest_square, side) {
-                            valid_moves.push(dest_square);
-                        }
-                    }
-                }
-            }
-        }
-        valid_moves
+        self.targets(from).collect()
     }
 
     /// Compute and return all the legal moves for a [`Piece`] (if exist) at a given [`Square`].
     ///
     /// If no [`Piece`] exist on the [`Square`], then return an empty [`Vec`].
     pub fn get_legal_moves(&self, from: Square) -> Vec<Square> {
-        let mut valid_moves = Vec::new();
-        if let Some((piece_from, side)) = self.on(from) {
-            if side == self.side_to_move {
-                let mut dest_square;
-                match piece_from {
-                    Piece::Pawn => {
-                        // If square forward is is empty
-                        dest_square = from.forward(side);
-                        if self.is_empty(dest_square) {
-                            if !self.is_exposing_move(Move::new(from, dest_square)) {
-                                valid_moves.push(dest_square);
-                            }
-
-                            // First move of the pawn
-                            dest_square = dest_square.forward(side);
-                            if from.rank_for(side) == Rank::Second
-                                && self.is_empty(dest_square)
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-
-                        {
-                            dest_square = from.forward(side).right();
-                            if (self.color_on_is(dest_square, !side)
-                                || Some(dest_square) == self.en_passant)
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                            dest_square = from.forward(side).left();
-                            if (self.color_on_is(dest_square, !side)
-                                || Some(dest_square) == self.en_passant)
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-                    }
-                    Piece::Knight => {
-                        let knight_moves_rule = vec![
-                            from.up().up().left(),
-                            from.up().up().right(),
-                            from.right().right().up(),
-                            from.right().right().down(),
-                            from.down().down().right(),
-                            from.down().down().left(),
-                            from.left().left().down(),
-                            from.left().left().up(),
-                        ];
-
-                        for square in knight_moves_rule {
-                            if from.distance(square) == 2
-                                && !self.color_on_is(square, side)
-                                && !self.is_exposing_move(Move::new(from, square))
-                            {
-                                valid_moves.push(square);
-                            }
-                        }
-                    }
-                    Piece::Bishop => {
-                        for direction in ALL_DIAGONAL {
-                            match from.rank() {
-                                Rank::First if direction.has(Direction::Down) => continue,
-                                Rank::Eighth if direction.has(Direction::Up) => continue,
-                                _ => {}
-                            }
-                            match from.file() {
-                                File::A if direction.has(Direction::Left) => continue,
-                                File::H if direction.has(Direction::Right) => continue,
-                                _ => {}
-                            }
-                            let mut old_square = from;
-                            dest_square = old_square.follow_direction(direction);
-                            while self.is_empty(dest_square)
-                                && old_square.distance(dest_square) == 1
-                            {
-                                if !self.is_exposing_move(Move::new(from, dest_square)) {
-                                    valid_moves.push(dest_square);
-                                }
-                                old_square = dest_square;
-                                dest_square = dest_square.follow_direction(direction);
-                            }
-                            if self.color_on_is(dest_square, !side)
-                                && old_square.distance(dest_square) == 1
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-                    }
-                    Piece::Rook => {
-                        for direction in ALL_LINE {
-                            match from.rank() {
-                                Rank::First if direction.has(Direction::Down) => continue,
-                                Rank::Eighth if direction.has(Direction::Up) => continue,
-                                _ => {}
-                            }
-                            match from.file() {
-                                File::A if direction.has(Direction::Left) => continue,
-                                File::H if direction.has(Direction::Right) => continue,
-                                _ => {}
-                            }
-                            let mut old_square = from;
-                            dest_square = old_square.follow_direction(direction);
-                            while self.is_empty(dest_square)
-                                && old_square.distance(dest_square) == 1
-                            {
-                                if !self.is_exposing_move(Move::new(from, dest_square)) {
-                                    valid_moves.push(dest_square);
-                                }
-                                old_square = dest_square;
-                                dest_square = dest_square.follow_direction(direction);
-                            }
-                            if self.color_on_is(dest_square, !side)
-                                && old_square.distance(dest_square) == 1
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-                    }
-                    Piece::Queen => {
-                        for direction in ALL_DIRECTION {
-                            match from.rank() {
-                                Rank::First if direction.has(Direction::Down) => continue,
-                                Rank::Eighth if direction.has(Direction::Up) => continue,
-                                _ => {}
-                            }
-                            match from.file() {
-                                File::A if direction.has(Direction::Left) => continue,
-                                File::H if direction.has(Direction::Right) => continue,
-                                _ => {}
-                            }
-                            let mut old_square = from;
-                            dest_square = old_square.follow_direction(direction);
-                            while self.is_empty(dest_square)
-                                && old_square.distance(dest_square) == 1
-                            {
-                                if !self.is_exposing_move(Move::new(from, dest_square)) {
-                                    valid_moves.push(dest_square);
-                                }
-                                old_square = dest_square;
-                                dest_square = dest_square.follow_direction(direction);
-                            }
-                            if self.color_on_is(dest_square, !side)
-                                && old_square.distance(dest_square) == 1
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-                    }
-                    Piece::King => {
-                        for direction in ALL_DIRECTION {
-                            match from.rank() {
-                                Rank::First if direction.has(Direction::Down) => continue,
-                                Rank::Eighth if direction.has(Direction::Up) => continue,
-                                _ => {}
-                            }
-                            match from.file() {
-                                File::A if direction.has(Direction::Left) => continue,
-                                File::H if direction.has(Direction::Right) => continue,
-                                _ => {}
-                            }
-                            dest_square = from.follow_direction(direction);
-                            if !self.color_on_is(dest_square, side)
-                                && !self.is_exposing_move(Move::new(from, dest_square))
-                            {
-                                valid_moves.push(dest_square);
-                            }
-                        }
-                        if self.castle_rights(side).has_kingside()
-                            && !self.color_on_is(from.right().right(), side)
-                            && !self.color_on_is(from.right(), side)
-                            && !self.is_targeted(from, !side)
-                            && !self.is_targeted(from.right(), !side)
-                            && !self.is_targeted(from.right().right(), !side)
-                        {
-                            valid_moves.push(from.right().right());
-                        }
-                        if self.castle_rights(side).has_queenside()
-                            && !self.color_on_is(from.left().left(), side)
-                            && !self.color_on_is(from.left(), side)
-                            && !self.is_targeted(from, !side)
-                            && !self.is_targeted(from.left(), !side)
-                            && !self.is_targeted(from.left().left(), !side)
-                            && !self.is_targeted(from.left().left().left(), !side)
-                        {
-                            valid_moves.push(from.left().left());
-                        }
-                    }
+        let mut moves = Vec::new();
+        self.legal_moves_from(from, &mut moves);
+        let mut legal_moves: Vec<Square> = moves.into_iter().map(|m| m.to).collect();
+        // Promotions give the same destination several times in a row
+        legal_moves.dedup();
+        legal_moves
+    }
+
+    /// Compute all the legal moves of the side to move.
+    ///
+    /// A pawn reaching the last rank gives one [`Move`] per promotion [`Piece`].
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        for from in self.color_combined(self.side_to_move) {
+            self.legal_moves_from(from, &mut moves);
+        }
+        moves
+    }
+
+    /// Push the legal moves of the [`Piece`] on `from` (if it belongs to the side to move).
+    fn legal_moves_from(&self, from: Square, moves: &mut Vec<Move>) {
+        let side = self.side_to_move;
+        let Some((piece, color)) = self.on(from) else {
+            return;
+        };
+        if color != side {
+            return;
+        }
+
+        let promotes = piece == Piece::Pawn && from.rank_for(side) == Rank::Seventh;
+        for to in self.targets(from) {
+            let m = Move::new(from, to);
+            if self.is_exposing_move(m) {
+                continue;
+            }
+            if promotes {
+                moves.extend(PROMOTION_PIECES.map(|promotion| m.with_promotion(promotion)));
+            } else {
+                moves.push(m);
+            }
+        }
+        if piece == Piece::King {
+            moves.extend(
+                self.castling_moves(from)
+                    .into_iter()
+                    .filter(|m| !self.is_exposing_move(*m)),
+            );
+        }
+    }
+
+    /// Destinations of the [`Piece`] on `from`, without looking at the safety of its king.
+    ///
+    /// Castling is not included.
+    fn targets(&self, from: Square) -> BitBoard {
+        match self.on(from) {
+            None => EMPTY,
+            Some((Piece::Pawn, side)) => {
+                let mut captures = self.color_combined(!side);
+                if let Some(en_passant) = self.en_passant.filter(|_| side == self.side_to_move) {
+                    captures |= BitBoard::from_square(en_passant);
                 }
+                self.pawn_pushes(from, side) | (pawn_attacks(side, from) & captures)
+            }
+            Some((piece, side)) => {
+                self.attacks_from(from, piece, side) & !self.color_combined(side)
             }
         }
-        valid_moves
+    }
+
+    /// Squares attacked by a [`Piece`] of the given [`Color`] standing on `from`.
+    fn attacks_from(&self, from: Square, piece: Piece, side: Color) -> BitBoard {
+        let occupied = self.occupied();
+        match piece {
+            Piece::Pawn => pawn_attacks(side, from),
+            Piece::Knight => knight_attacks(from),
+            Piece::Bishop => bishop_attacks(from, occupied),
+            Piece::Rook => rook_attacks(from, occupied),
+            Piece::Queen => queen_attacks(from, occupied),
+            Piece::King => king_attacks(from),
+        }
+    }
+
+    /// Non capturing destinations of a pawn: one square forward, or two from its initial rank.
+    fn pawn_pushes(&self, from: Square, side: Color) -> BitBoard {
+        let mut pushes = EMPTY;
+        let one = from.forward(side);
+        if one != from && self.is_empty(one) {
+            pushes |= BitBoard::from_square(one);
+            let two = one.forward(side);
+            if from.rank_for(side) == Rank::Second && self.is_empty(two) {
+                pushes |= BitBoard::from_square(two);
+            }
+        }
+        pushes
+    }
+
+    /// Castling moves of the king on `from`.
+    ///
+    /// The squares between the king and the rook must be empty, and the king can neither be
+    /// in check nor cross an attacked square. Whether it lands in check is left to the caller.
+    fn castling_moves(&self, from: Square) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let side = self.side_to_move;
+        let back_rank = from.rank();
+        let square = |file: File| Square::make_square(file, back_rank);
+        if from != square(File::E)
+            || from.rank_for(side) != Rank::First
+            || self.is_targeted(from, !side)
+        {
+            return moves;
+        }
+
+        let is_free = |files: &[File]| files.iter().all(|file| self.is_empty(square(*file)));
+        let rights = self.castle_rights(side);
+        if rights.has_kingside()
+            && self.on_is(square(File::H), (Piece::Rook, side))
+            && is_free(&[File::F, File::G])
+            && self.is_not_targeted(square(File::F), !side)
+        {
+            moves.push(Move::new(from, square(File::G)));
+        }
+        if rights.has_queenside()
+            && self.on_is(square(File::A), (Piece::Rook, side))
+            && is_free(&[File::B, File::C, File::D])
+            && self.is_not_targeted(square(File::D), !side)
+        {
+            moves.push(Move::new(from, square(File::C)));
+        }
+        moves
     }
 
     /// Construct a [`Vec`] of [`Square`] from a [`Square`] (exclusive) to the first [`Piece`]
@@ -905,63 +754,87 @@ impl FromStr for Board {
                     cur_file = File::new(cur_file.as_index() + (x as usize) - ('0' as usize));
                 }
                 'r' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Rook, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Rook, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'R' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Rook, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Rook, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'n' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Knight, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Knight, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'N' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Knight, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Knight, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'b' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Bishop, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Bishop, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'B' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Bishop, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Bishop, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'p' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Pawn, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Pawn, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'P' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Pawn, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Pawn, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'q' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Queen, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Queen, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'Q' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::Queen, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::Queen, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'k' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::King, Color::Black));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::King, Color::Black)),
+                    );
                     cur_file = cur_file.right();
                 }
                 'K' => {
-                    board[Square::make_square(cur_file, cur_rank)] =
-                        Some((Piece::King, Color::White));
+                    board.set(
+                        Square::make_square(cur_file, cur_rank),
+                        Some((Piece::King, Color::White)),
+                    );
                     cur_file = cur_file.right();
                 }
                 _ => {
@@ -1094,12 +967,6 @@ impl Index<Square> for Board {
     }
 }
 
-impl IndexMut<Square> for Board {
-    fn index_mut(&mut self, index: Square) -> &mut Self::Output {
-        &mut self.squares[index.as_index()]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::common::r#move::Move;
@@ -1114,7 +981,6 @@ mod tests {
 
     #[test]
     #[ignore]
-
     fn is_valid() {
         todo!()
     }
@@ -1366,9 +1232,130 @@ mod tests {
     #[test]
     fn check_legal() {
         let board = Board::default();
-        assert!(board.is_legal(Move {
-            from: Square::E2,
-            to: Square::E4,
-        }));
+        assert!(board.is_legal(Move::new(Square::E2, Square::E4)));
+        assert!(!board.is_legal(Move::new(Square::E2, Square::E4).with_promotion(Piece::Queen)));
+    }
+
+    #[test]
+    fn status() {
+        let status = |fen: &str| Board::from_str(fen).unwrap().status();
+
+        assert_eq!(Board::default().status(), GameStatus::Ongoing);
+        // Fool's mate
+        assert_eq!(
+            status("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+            GameStatus::Checkmate(Color::Black)
+        );
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2B5/4K3/8 w - - 0 1"),
+            GameStatus::InsufficientMaterial
+        );
+        // Both bishops on dark squares
+        assert_eq!(
+            status("8/8/4k3/4b3/8/2B5/4K3/8 w - - 0 1"),
+            GameStatus::InsufficientMaterial
+        );
+        assert_eq!(
+            status("8/8/4k3/3b4/8/2B5/4K3/8 w - - 0 1"),
+            GameStatus::Ongoing
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2N5/4K1N1/8 w - - 0 1"),
+            GameStatus::Ongoing
+        );
+        assert_eq!(
+            status("8/8/4k3/8/8/2R5/4K3/8 w - - 100 80"),
+            GameStatus::FiftyMoves
+        );
+        // Mate on the hundredth half move still counts
+        assert_eq!(
+            status("R6k/8/6K1/8/8/8/8/8 b - - 100 80"),
+            GameStatus::Checkmate(Color::White)
+        );
+    }
+
+    #[test]
+    fn underpromotion() {
+        let mut board = Board::from_str("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = Move::new(Square::E7, Square::D8);
+        assert!(board.is_legal(m.with_promotion(Piece::Knight)));
+        assert!(!board.is_legal(m.with_promotion(Piece::King)));
+        assert!(!board.is_legal(m.with_promotion(Piece::Pawn)));
+
+        board.update(m.with_promotion(Piece::Knight));
+        assert_eq!(board.on(Square::D8), Some((Piece::Knight, Color::White)));
+        assert_eq!(board.to_string(), "3N3k/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn legal_moves() {
+        assert_eq!(Board::default().legal_moves().len(), 20);
+
+        // Kiwipete
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(Board::from_str(fen).unwrap().legal_moves().len(), 48);
+
+        // Each promotion piece is a move of its own
+        let board = Board::from_str("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotions = board
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.from == Square::E7)
+            .count();
+        assert_eq!(promotions, 8);
+    }
+
+    #[test]
+    fn castling() {
+        let castles = |fen: &str| {
+            let board = Board::from_str(fen).unwrap();
+            board
+                .get_legal_moves(Square::E1)
+                .into_iter()
+                .filter(|to| Square::E1.distance(*to) == 2)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"),
+            vec![Square::G1, Square::C1]
+        );
+        // Blocked by a piece, even an enemy one
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/Rn2K2R w KQ - 0 1"),
+            vec![Square::G1]
+        );
+        // Crossing an attacked square
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1"),
+            vec![Square::C1]
+        );
+        // In check
+        assert_eq!(castles("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1"), vec![]);
+        // Only b1 attacked: the king doesn't cross it
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/1r6/R3K2R w KQ - 0 1"),
+            vec![Square::G1, Square::C1]
+        );
+    }
+
+    #[test]
+    fn capturing_a_rook_removes_castle_rights() {
+        let mut board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        board.update(Move::new(Square::H1, Square::H8));
+        assert_eq!(board.castle_rights(Color::White), CastleRights::QueenSide);
+        assert_eq!(board.castle_rights(Color::Black), CastleRights::QueenSide);
+    }
+
+    #[test]
+    fn en_passant_discovering_check() {
+        // Taking en passant would leave both pawns off the fifth rank
+        let board = Board::from_str("8/8/8/KPp4r/8/8/8/7k w - c6 0 1").unwrap();
+        assert!(!board.is_legal(Move::new(Square::B5, Square::C6)));
+        assert!(board.is_legal(Move::new(Square::B5, Square::B6)));
     }
 }
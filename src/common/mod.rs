@@ -1,5 +1,7 @@
 pub use crate::prelude::*;
 
+pub mod attacks;
+pub mod bitboard;
 pub mod board;
 pub mod castle_rights;
 pub mod color;
@@ -7,6 +9,8 @@ pub mod direction;
 pub mod file;
 
 pub mod r#move;
+pub mod perft;
 pub mod piece;
 pub mod rank;
 pub mod square;
+pub mod status;
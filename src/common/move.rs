@@ -1,12 +1,15 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 use crate::{
     common::{color::Color, rank::Rank},
     error::{Error, ParseKind},
-    logic::movetree::treenode::{CMove, CMoveKind, CastleSide, MoveDetails},
+    logic::movetree::{
+        commands::Commands,
+        treenode::{CMove, CMoveKind, CastleSide, MoveDetails},
+    },
 };
 
-use super::{board::Board, piece::Piece, square::Square};
+use super::{board::Board, piece::Piece, square::Square, status::GameStatus};
 use crate::Result;
 
 /// Represent a Move
@@ -16,13 +19,28 @@ pub struct Move {
     pub from: Square,
     /// The [`Square`] where the [`Piece`] is going to.
     pub to: Square,
+    /// The [`Piece`] a pawn turns into on the last rank, a queen if not given.
+    pub promotion: Option<Piece>,
 }
 
 impl Move {
     /// Create a new chess move.
     #[inline]
     pub fn new(from: Square, to: Square) -> Self {
-        Move { from, to }
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    /// The same move, promoting the pawn to `piece`.
+    #[inline]
+    pub fn with_promotion(self, piece: Piece) -> Self {
+        Move {
+            promotion: Some(piece),
+            ..self
+        }
     }
 
     /// The distance between the two [`Square`] of the move.
@@ -39,7 +57,7 @@ impl Move {
     }
 
     pub fn try_into_cmove(self, board: Board) -> Result<CMove> {
-        let Self { from, to } = self;
+        let Self { from, to, .. } = self;
         if let Some((piece, color)) = board.colored_piece_on(from) {
             let kind = match (color, piece, from, to) {
                 (Color::White, Piece::King, Square::E1, Square::G1) => {
@@ -55,14 +73,16 @@ impl Move {
                     CMoveKind::Castles(CastleSide::Long)
                 }
                 _ => {
-                    let captures = if let Some((_, captured_color)) = board.colored_piece_on(to) {
-                        captured_color != board.side_to_move()
-                    } else {
-                        false
+                    let captures = match board.colored_piece_on(to) {
+                        Some((_, captured_color)) => captured_color != board.side_to_move(),
+                        // En passant takes a pawn that isn't on the square moved to
+                        None => piece == Piece::Pawn && board.en_passant() == Some(to),
                     };
                     let promotion = match (piece, color, to.rank()) {
                         (Piece::Pawn, Color::Black, Rank::First)
-                        | (Piece::Pawn, Color::White, Rank::Eighth) => Some(Piece::Queen),
+                        | (Piece::Pawn, Color::White, Rank::Eighth) => {
+                            Some(self.promotion.unwrap_or(Piece::Queen))
+                        }
                         _ => None,
                     };
 
@@ -87,56 +107,140 @@ impl Move {
             };
             let next_board = board.clone().update(self);
             let check = next_board.is_check();
-            let checkmate = next_board.is_checkmate();
+            let checkmate = matches!(next_board.status(), GameStatus::Checkmate(_));
             return Ok(CMove {
                 move_number: board.fullmoves() as usize,
                 kind,
                 check,
                 color: board.side_to_move(),
                 checkmate,
-                comment: None,
+                comments_before: vec![],
+                comments_after: vec![],
+                commands: Commands::default(),
+                nags: vec![],
             });
         };
         Err(Error::ParseError(ParseKind::MoveToCMove))
     }
 
+    /// The move in SAN, written as [`CMove::to_san`] writes it.
     pub fn as_notation(self, board: &Board) -> String {
-        let Move { from, to } = self;
-        let piece = board.piece_on(from).unwrap();
-        let mut move_text = String::new();
-        if piece == Piece::King {
-            if (from, to) == (Square::E1, Square::G1) || (from, to) == (Square::E8, Square::G8) {
-                return String::from("0-0");
-            } else if (from, to) == (Square::E1, Square::C1)
-                || (from, to) == (Square::E8, Square::C8)
-            {
-                return String::from("0-0-0");
-            }
-        } else {
-            let is_capture = board.piece_on(to).is_some();
-            match piece {
-                Piece::Pawn => {
-                    move_text = if is_capture {
-                        format!("{}x{}", from.file().as_str(), to)
-                    } else {
-                        format!("{to}")
-                    }
-                }
-                _ => {
-                    move_text = if is_capture {
-                        format!("{piece}x{to}")
-                    } else {
-                        format!("{piece}{to}")
+        self.try_into_cmove(*board)
+            .map(|cmove| cmove.to_san())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(piece) => write!(f, "{}", piece.as_fen_string(Color::Black)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = Error;
+
+    /// From UCI long algebraic notation (e.g. `e2e4` or `e7e8n`).
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |_| Error::ParseError(ParseKind::StrToMove);
+        let m = match (s.get(0..2), s.get(2..4)) {
+            (Some(from), Some(to)) => Move::new(
+                Square::from_str(from).map_err(invalid)?,
+                Square::from_str(to).map_err(invalid)?,
+            ),
+            _ => return Err(Error::ParseError(ParseKind::StrToMove)),
+        };
+        match s
+            .get(4..)
+            .map(|promotion| promotion.chars().collect::<Vec<_>>())
+        {
+            Some(promotion) if promotion.is_empty() => Ok(m),
+            Some(promotion) if promotion.len() == 1 => {
+                match Piece::try_from(&promotion[0].to_ascii_uppercase()) {
+                    Ok(piece @ (Piece::Knight | Piece::Bishop | Piece::Rook | Piece::Queen)) => {
+                        Ok(m.with_promotion(piece))
                     }
+                    _ => Err(Error::ParseError(ParseKind::StrToMove)),
                 }
             }
+            _ => Err(Error::ParseError(ParseKind::StrToMove)),
         }
-        move_text
     }
 }
 
-impl fmt::Display for Move {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", self.from, self.to)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notation() {
+        let board = Board::from_str("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(Move::new(Square::E1, Square::G1).as_notation(&board), "O-O");
+        assert_eq!(
+            Move::new(Square::E1, Square::C1).as_notation(&board),
+            "O-O-O"
+        );
+        assert_eq!(Move::new(Square::E1, Square::E2).as_notation(&board), "Ke2");
+        assert_eq!(
+            Move::new(Square::B7, Square::A8)
+                .with_promotion(Piece::Knight)
+                .as_notation(&board),
+            "bxa8=N"
+        );
+    }
+
+    #[test]
+    fn from_uci() {
+        assert_eq!(
+            Move::from_str("e2e4"),
+            Ok(Move::new(Square::E2, Square::E4))
+        );
+        assert_eq!(
+            Move::from_str("e2"),
+            Err(Error::ParseError(ParseKind::StrToMove))
+        );
+        assert_eq!(
+            Move::from_str("i2e4"),
+            Err(Error::ParseError(ParseKind::StrToMove))
+        );
+        assert_eq!(
+            Move::from_str("e7e8n"),
+            Ok(Move::new(Square::E7, Square::E8).with_promotion(Piece::Knight))
+        );
+        assert_eq!(
+            Move::from_str("e7e8k"),
+            Err(Error::ParseError(ParseKind::StrToMove))
+        );
+        assert_eq!(
+            Move::new(Square::B2, Square::A1)
+                .with_promotion(Piece::Rook)
+                .to_string(),
+            "b2a1r"
+        );
+    }
+
+    #[test]
+    fn cmove_keeps_promotion() {
+        let board = Board::from_str("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let cmove = Move::new(Square::E7, Square::E8)
+            .with_promotion(Piece::Knight)
+            .try_into_cmove(board)
+            .unwrap();
+        assert_eq!(cmove.to_san(), "e8=N");
+    }
+
+    #[test]
+    fn cmove_of_en_passant_captures() {
+        let board =
+            Board::from_str("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+                .unwrap();
+        let cmove = Move::new(Square::E5, Square::D6)
+            .try_into_cmove(board)
+            .unwrap();
+        assert_eq!(cmove.to_san(), "exd6");
     }
 }
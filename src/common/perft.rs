@@ -0,0 +1,109 @@
+//! Move generation testing, by counting the nodes of the move tree.
+//!
+//! > reference: <https://www.chessprogramming.org/Perft_Results>
+
+use super::board::Board;
+use super::r#move::Move;
+
+impl Board {
+    /// Count the positions reached after `depth` plies from this one.
+    pub fn perft(&self, depth: u32) -> u64 {
+        match depth {
+            0 => 1,
+            // No need to play the last moves to count them
+            1 => self.legal_moves().len() as u64,
+            _ => self
+                .legal_moves()
+                .into_iter()
+                .map(|m| self.play(m).perft(depth - 1))
+                .sum(),
+        }
+    }
+
+    /// [`Board::perft`] split by first move, to find which one the generator gets wrong.
+    ///
+    /// No move is played at depth 0, so there is nothing to split.
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return vec![];
+        }
+        self.legal_moves()
+            .into_iter()
+            .map(|m| (m, self.play(m).perft(depth - 1)))
+            .collect()
+    }
+
+    fn play(&self, m: Move) -> Board {
+        let mut next_board = *self;
+        next_board.update(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn perft(fen: &str, depth: u32) -> u64 {
+        Board::from_str(fen).expect("valid fen").perft(depth)
+    }
+
+    #[test]
+    fn initial_position() {
+        let board = Board::default();
+        assert_eq!(board.perft(0), 1);
+        assert_eq!(board.perft(1), 20);
+        assert_eq!(board.perft(2), 400);
+        assert_eq!(board.perft(3), 8_902);
+        assert_eq!(board.perft(4), 197_281);
+    }
+
+    #[test]
+    fn kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(perft(fen, 1), 48);
+        assert_eq!(perft(fen, 2), 2_039);
+        assert_eq!(perft(fen, 3), 97_862);
+    }
+
+    #[test]
+    fn en_passant_and_pins() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(perft(fen, 1), 14);
+        assert_eq!(perft(fen, 2), 191);
+        assert_eq!(perft(fen, 3), 2_812);
+        assert_eq!(perft(fen, 4), 43_238);
+    }
+
+    #[test]
+    fn castling_and_promotions() {
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(perft(fen, 1), 6);
+        assert_eq!(perft(fen, 2), 264);
+        assert_eq!(perft(fen, 3), 9_467);
+
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        assert_eq!(perft(fen, 1), 44);
+        assert_eq!(perft(fen, 2), 1_486);
+        assert_eq!(perft(fen, 3), 62_379);
+    }
+
+    #[test]
+    fn middlegame() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        assert_eq!(perft(fen, 1), 46);
+        assert_eq!(perft(fen, 2), 2_079);
+        assert_eq!(perft(fen, 3), 89_890);
+    }
+
+    #[test]
+    fn divide() {
+        let divide = Board::default().divide(2);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 20));
+
+        assert_eq!(Board::default().divide(1).len(), 20);
+        assert!(Board::default().divide(0).is_empty());
+    }
+}
@@ -15,11 +15,15 @@ pub enum Piece {
     King,
 }
 
+/// Numbers of [`Piece`].
+pub const NUM_PIECES: usize = 6;
+
 /// Number of promotion.
 pub const NUM_PROMOTION_PIECES: usize = 4;
 
-// /// Enumerate all [`Piece`] in which a [`Piece::Pawn`] can be promoted.
-// pub const PROMOTION_PIECES: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+/// Enumerate all [`Piece`] in which a [`Piece::Pawn`] can be promoted.
+pub const PROMOTION_PIECES: [Piece; NUM_PROMOTION_PIECES] =
+    [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
 
 impl Piece {
     /// Convert the [`Piece`] to a [`usize`].
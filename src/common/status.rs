@@ -0,0 +1,52 @@
+use std::fmt;
+
+use crate::logic::movetree::treenode::CResult;
+
+use super::color::Color;
+
+/// State of a game in a given position.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GameStatus {
+    Ongoing,
+    /// The game was won by the given side.
+    Checkmate(Color),
+    Stalemate,
+    InsufficientMaterial,
+    /// 50 moves by each side without a capture or a pawn move.
+    FiftyMoves,
+    ThreefoldRepetition,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    /// The result the game ended with, if it ended without either side having to claim it.
+    ///
+    /// A draw by the fifty-move rule or by threefold repetition has to be claimed, so the game
+    /// may go on.
+    pub fn result(&self) -> Option<CResult> {
+        match self {
+            GameStatus::Ongoing | GameStatus::FiftyMoves | GameStatus::ThreefoldRepetition => None,
+            GameStatus::Checkmate(Color::White) => Some(CResult::WhiteWins),
+            GameStatus::Checkmate(Color::Black) => Some(CResult::BlackWins),
+            GameStatus::Stalemate | GameStatus::InsufficientMaterial => Some(CResult::Draw),
+        }
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            GameStatus::Ongoing => "",
+            GameStatus::Checkmate(Color::White) => "Checkmate, White wins",
+            GameStatus::Checkmate(Color::Black) => "Checkmate, Black wins",
+            GameStatus::Stalemate => "Draw by stalemate",
+            GameStatus::InsufficientMaterial => "Draw by insufficient material",
+            GameStatus::FiftyMoves => "Draw by the fifty-move rule",
+            GameStatus::ThreefoldRepetition => "Draw by threefold repetition",
+        };
+        write!(f, "{status}")
+    }
+}
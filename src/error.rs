@@ -1,5 +1,4 @@
-use crate::logic::movetree::treenode::CMove;
-
+#[allow(clippy::enum_variant_names)]
 #[derive(PartialEq, Debug, Clone)]
 pub enum Error {
     Comm,
@@ -14,11 +13,20 @@ pub enum Error {
     InvalidPgn { pgn: String },
     ParseError(ParseKind),
     PgnGeneration,
-    FenGeneration { fen: String, cmove: CMove },
+    Engine(EngineKind),
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum ParseKind {
     CharToPiece,
     MoveToCMove,
+    StrToMove,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum EngineKind {
+    Spawn,
+    Io,
+    Handshake,
+    Terminated,
 }
@@ -0,0 +1,50 @@
+use std::{fs, path::PathBuf};
+
+use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialogResult};
+
+/// Ask for a PGN file and read it.
+///
+/// Returns `None` when no file is picked, and why the file couldn't be read.
+pub async fn open_pgn() -> Result<Option<(PathBuf, String)>, String> {
+    let Some(handle) = AsyncFileDialog::new()
+        .add_filter("PGN", &["pgn"])
+        .pick_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    let path = handle.path().to_path_buf();
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(Some((path, content)))
+}
+
+/// Write `content` to `path`, asking where to save it when there is no path yet.
+///
+/// Returns `None` when no file is picked, and why the file couldn't be written.
+pub async fn save_pgn(path: Option<PathBuf>, content: String) -> Result<Option<PathBuf>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => match AsyncFileDialog::new()
+            .add_filter("PGN", &["pgn"])
+            .set_file_name("game.pgn")
+            .save_file()
+            .await
+        {
+            Some(handle) => handle.path().to_path_buf(),
+            None => return Ok(None),
+        },
+    };
+    fs::write(&path, content).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(Some(path))
+}
+
+/// Whether the user is fine with losing their unsaved changes.
+pub async fn confirm_discard() -> bool {
+    let answer = AsyncMessageDialog::new()
+        .set_title("Unsaved changes")
+        .set_description("The current game has unsaved changes. Discard them?")
+        .set_buttons(MessageButtons::YesNo)
+        .show()
+        .await;
+    answer == MessageDialogResult::Yes
+}
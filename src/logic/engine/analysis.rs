@@ -0,0 +1,369 @@
+//! Continuous analysis of the displayed position, fed to the GUI through a [`Subscription`].
+
+use std::{
+    any::TypeId,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use iced::{
+    futures::{channel::mpsc::UnboundedSender, SinkExt, StreamExt},
+    subscription, Subscription,
+};
+
+use crate::{
+    common::{board::Board, color::Color, r#move::Move},
+    error::Error,
+};
+
+use super::{
+    uci::{Info, Score, UciMessage},
+    Engine, SearchLimit,
+};
+
+/// Number of principal variations we ask the engine for.
+pub const MULTI_PV: u32 = 3;
+
+/// How often the engine thread checks for new requests while the engine is quiet.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Ready(Sender<Request>),
+    /// The engine is up, with the name it gave.
+    Started(Option<String>),
+    Info(Box<(Board, Info)>),
+    Failed(Error),
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    Analyse(Box<Board>, SearchLimit),
+    /// Forget the positions analysed so far, they were from another game.
+    NewGame,
+    /// Start the engine again, e.g. when it stopped answering sensibly.
+    Restart,
+}
+
+/// Start the engine binary and stream its analysis for as long as the subscription is alive.
+pub fn subscription(program: String) -> Subscription<Event> {
+    subscription::channel(
+        (TypeId::of::<Event>(), program.clone()),
+        100,
+        |mut output| async move {
+            let (request_sender, requests) = mpsc::channel();
+            let (event_sender, mut events) = iced::futures::channel::mpsc::unbounded();
+
+            // The engine is driven with blocking I/O, so it gets a thread of its own
+            thread::spawn(move || supervise(program, requests, event_sender));
+            let _ = output.send(Event::Ready(request_sender)).await;
+
+            while let Some(event) = events.next().await {
+                let _ = output.send(event).await;
+            }
+
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+fn supervise(program: String, requests: Receiver<Request>, events: UnboundedSender<Event>) {
+    let mut engine = match Engine::spawn(&program, [""; 0]) {
+        Ok(engine) => engine,
+        Err(e) => {
+            let _ = events.unbounded_send(Event::Failed(e));
+            return;
+        }
+    };
+    if let Err(e) = configure(&mut engine, &events) {
+        let _ = events.unbounded_send(Event::Failed(e));
+        return;
+    }
+
+    let mut position = None;
+    loop {
+        let handled = match requests.try_recv() {
+            Ok(Request::Analyse(board, limit)) => {
+                position = Some(*board);
+                engine.go(&board, limit)
+            }
+            // The GUI asks for the position again afterwards
+            Ok(Request::NewGame) => {
+                position = None;
+                engine.new_game()
+            }
+            Ok(Request::Restart) => {
+                position = None;
+                engine
+                    .restart()
+                    .and_then(|_| configure(&mut engine, &events))
+            }
+            Err(TryRecvError::Empty) => Ok(()),
+            // The GUI no longer wants analysis
+            Err(TryRecvError::Disconnected) => return,
+        };
+
+        let event = match handled.and_then(|_| engine.recv_timeout(POLL_INTERVAL)) {
+            Ok(Some(UciMessage::Info(info))) if info.depth.is_some() && !info.pv.is_empty() => {
+                match position {
+                    Some(board) => Event::Info(Box::new((board, info))),
+                    None => continue,
+                }
+            }
+            Ok(_) => continue,
+            Err(e) => Event::Failed(e),
+        };
+
+        let failed = matches!(event, Event::Failed(_));
+        if events.unbounded_send(event).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Set up a freshly started engine and tell the GUI about it.
+fn configure(engine: &mut Engine, events: &UnboundedSender<Event>) -> crate::Result<()> {
+    engine.set_option("MultiPV", &MULTI_PV.to_string())?;
+    let _ = events.unbounded_send(Event::Started(engine.name().map(String::from)));
+    Ok(())
+}
+
+/// What the GUI knows about the analysis of the displayed position.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    enabled: bool,
+    requests: Option<Sender<Request>>,
+    /// Name of the engine, once it started.
+    name: Option<String>,
+    limit: SearchLimit,
+    board: Option<Board>,
+    /// The best lines, by their `multipv` index.
+    lines: [Option<Info>; MULTI_PV as usize],
+    error: Option<Error>,
+}
+
+impl Analysis {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        *self = Analysis {
+            enabled: !self.enabled,
+            limit: self.limit,
+            ..Default::default()
+        };
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn limit(&self) -> SearchLimit {
+        self.limit
+    }
+
+    /// Search every position with `limit` from now on, starting with the displayed one.
+    pub fn set_limit(&mut self, limit: SearchLimit) {
+        self.limit = limit;
+        self.board = None;
+    }
+
+    /// Let the engine know another game is shown, its positions being unrelated.
+    pub fn new_game(&mut self) {
+        self.send(Request::NewGame);
+    }
+
+    /// Start the engine again, then analyse the displayed position from scratch.
+    pub fn restart(&mut self) {
+        self.send(Request::Restart);
+    }
+
+    /// Send `request` to the engine, the displayed position being sent again after it.
+    fn send(&mut self, request: Request) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(request);
+            self.board = None;
+            self.lines = Default::default();
+        }
+    }
+
+    pub fn handle(&mut self, event: Event) {
+        match event {
+            Event::Ready(requests) => {
+                self.requests = Some(requests);
+                self.board = None;
+            }
+            Event::Started(name) => self.name = name,
+            Event::Info(update) if Some(update.0) == self.board => {
+                let (_, info) = *update;
+                let index = info.multipv.unwrap_or(1).saturating_sub(1) as usize;
+                if let Some(line) = self.lines.get_mut(index) {
+                    *line = Some(info);
+                }
+            }
+            Event::Info(_) => {}
+            Event::Failed(e) => {
+                *self = Analysis {
+                    error: Some(e),
+                    limit: self.limit,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// Make sure the engine is looking at `board`.
+    pub fn follow(&mut self, board: &Board) {
+        if let Some(requests) = &self.requests {
+            if self.board != Some(*board) {
+                let _ = requests.send(Request::Analyse(Box::new(*board), self.limit));
+                self.board = Some(*board);
+                self.lines = Default::default();
+            }
+        }
+    }
+
+    /// The best lines, a line being `None` until the engine reports it.
+    pub fn lines(&self) -> &[Option<Info>] {
+        &self.lines
+    }
+
+    pub fn depth(&self) -> Option<u32> {
+        self.lines[0].as_ref().and_then(|line| line.depth)
+    }
+
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// The evaluation of the best line, from White's point of view.
+    pub fn score_for_white(&self) -> Option<Score> {
+        let score = self.lines[0].as_ref()?.score?;
+        match self.board?.side_to_move() {
+            Color::White => Some(score),
+            Color::Black => Some(match score {
+                Score::Cp(cp) => Score::Cp(-cp),
+                Score::Mate(moves) => Score::Mate(-moves),
+            }),
+        }
+    }
+}
+
+/// Convert a line of engine moves into SAN, stopping at the first move that isn't legal.
+pub fn san_line(board: &Board, pv: &[Move]) -> Vec<String> {
+    let mut board = *board;
+    let mut line = vec![];
+    for m in pv {
+        if !board.is_legal(*m) {
+            break;
+        }
+        match m.try_into_cmove(board) {
+            Ok(cmove) => line.push(cmove.to_san()),
+            Err(_) => break,
+        }
+        board.update(*m);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::common::square::Square;
+
+    use super::*;
+
+    #[test]
+    fn converts_pv_to_san() {
+        let pv = [
+            Move::new(Square::E2, Square::E4),
+            Move::new(Square::E7, Square::E5),
+            Move::new(Square::G1, Square::F3),
+            // Illegal, so the line ends here
+            Move::new(Square::E8, Square::E6),
+            Move::new(Square::B1, Square::C3),
+        ];
+        assert_eq!(san_line(&Board::default(), &pv), vec!["e4", "e5", "Nf3"]);
+    }
+
+    #[test]
+    fn asks_again_after_changing_the_engine() {
+        let (requests, received) = mpsc::channel();
+        let mut analysis = Analysis::default();
+        analysis.toggle();
+        analysis.handle(Event::Ready(requests));
+        analysis.handle(Event::Started(Some("Fake Engine".to_string())));
+        assert_eq!(analysis.name(), Some("Fake Engine"));
+
+        let board = Board::default();
+        analysis.follow(&board);
+        let depth = SearchLimit::Depth(20);
+        analysis.set_limit(depth);
+        analysis.follow(&board);
+        analysis.restart();
+        analysis.follow(&board);
+        analysis.new_game();
+        analysis.follow(&board);
+
+        let sent: Vec<_> = received.try_iter().collect();
+        assert!(matches!(
+            sent[..],
+            [
+                Request::Analyse(_, SearchLimit::Infinite),
+                Request::Analyse(_, a),
+                Request::Restart,
+                Request::Analyse(_, b),
+                Request::NewGame,
+                Request::Analyse(_, c),
+            ] if a == depth && b == depth && c == depth
+        ));
+
+        // The limit is kept when the engine is started again
+        analysis.toggle();
+        analysis.toggle();
+        assert_eq!(analysis.limit(), depth);
+    }
+
+    #[test]
+    fn keeps_lines_for_current_board_only() {
+        let (requests, received) = mpsc::channel();
+        let mut analysis = Analysis::default();
+        analysis.toggle();
+        analysis.handle(Event::Ready(requests));
+
+        let board = Board::default();
+        analysis.follow(&board);
+        assert!(matches!(
+            received.try_recv(),
+            Ok(Request::Analyse(b, SearchLimit::Infinite)) if *b == board
+        ));
+
+        let stale =
+            Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let info = |multipv, cp| Info {
+            depth: Some(10),
+            multipv: Some(multipv),
+            score: Some(Score::Cp(cp)),
+            pv: vec![Move::new(Square::E2, Square::E4)],
+            ..Default::default()
+        };
+        analysis.handle(Event::Info(Box::new((stale, info(1, 40)))));
+        assert!(analysis.lines().iter().all(Option::is_none));
+
+        // Lines keep their place whatever order they arrive in
+        analysis.handle(Event::Info(Box::new((board, info(3, 5)))));
+        analysis.handle(Event::Info(Box::new((board, info(1, 30)))));
+        analysis.handle(Event::Info(Box::new((board, info(1, 35)))));
+        let scores: Vec<_> = analysis
+            .lines()
+            .iter()
+            .map(|line| line.as_ref().and_then(|line| line.score))
+            .collect();
+        assert_eq!(scores, [Some(Score::Cp(35)), None, Some(Score::Cp(5))]);
+        assert_eq!(analysis.score_for_white(), Some(Score::Cp(35)));
+        assert_eq!(analysis.depth(), Some(10));
+    }
+}
@@ -0,0 +1,349 @@
+pub mod analysis;
+pub mod uci;
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt::Display,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    common::board::Board,
+    error::{EngineKind, Error},
+    Result,
+};
+
+use self::uci::{IdKind, UciMessage};
+
+/// How long we wait for `uciok`/`readyok`/`bestmove` before giving up on the engine.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the engine searches a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchLimit {
+    /// Until it is told to stop.
+    #[default]
+    Infinite,
+    /// Until it has looked that many plies ahead.
+    Depth(u32),
+    /// For that long.
+    MoveTime(Duration),
+}
+
+impl SearchLimit {
+    fn as_command(&self) -> String {
+        match self {
+            SearchLimit::Infinite => "go infinite".to_string(),
+            SearchLimit::Depth(depth) => format!("go depth {depth}"),
+            SearchLimit::MoveTime(time) => format!("go movetime {}", time.as_millis()),
+        }
+    }
+}
+
+impl Display for SearchLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchLimit::Infinite => write!(f, "No limit"),
+            SearchLimit::Depth(depth) => write!(f, "Depth {depth}"),
+            SearchLimit::MoveTime(time) => write!(f, "{} s", time.as_secs()),
+        }
+    }
+}
+
+/// Supervisor for a UCI engine running as a child process.
+///
+/// The engine's standard output is read on a background thread and handed over as parsed
+/// [`UciMessage`]s, so that polling it never blocks the caller.
+#[derive(Debug)]
+pub struct Engine {
+    program: OsString,
+    args: Vec<OsString>,
+    name: Option<String>,
+    process: Child,
+    stdin: ChildStdin,
+    output: Receiver<UciMessage>,
+    searching: bool,
+}
+
+impl Engine {
+    /// Start the engine binary and run the `uci`/`isready` handshake.
+    pub fn spawn<I, S>(program: impl AsRef<OsStr>, args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let program = program.as_ref().to_os_string();
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+
+        let mut process = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| Error::Engine(EngineKind::Spawn))?;
+
+        let stdin = process
+            .stdin
+            .take()
+            .ok_or(Error::Engine(EngineKind::Spawn))?;
+        let stdout = process
+            .stdout
+            .take()
+            .ok_or(Error::Engine(EngineKind::Spawn))?;
+
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(UciMessage::parse(&line)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            program,
+            args,
+            name: None,
+            process,
+            stdin,
+            output,
+            searching: false,
+        };
+        engine.handshake()?;
+        Ok(engine)
+    }
+
+    /// The name the engine reported through `id name`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    #[cfg(test)]
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    fn handshake(&mut self) -> Result<()> {
+        self.send("uci")?;
+        loop {
+            match self.wait_for_message()? {
+                UciMessage::Id(IdKind::Name, name) => self.name = Some(name),
+                UciMessage::UciOk => break,
+                _ => {}
+            }
+        }
+        self.is_ready()
+    }
+
+    /// Send `isready` and block until the engine answers `readyok`.
+    pub fn is_ready(&mut self) -> Result<()> {
+        self.send("isready")?;
+        while self.wait_for_message()? != UciMessage::ReadyOk {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        self.send(&format!("setoption name {name} value {value}"))?;
+        self.is_ready()
+    }
+
+    /// Tell the engine the next positions are from another game.
+    pub fn new_game(&mut self) -> Result<()> {
+        self.stop()?;
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Start searching the given position, stopping any search already running.
+    pub fn go(&mut self, board: &Board, limit: SearchLimit) -> Result<()> {
+        self.stop()?;
+        self.send(&format!("position fen {board}"))?;
+        self.send(&limit.as_command())?;
+        self.searching = true;
+        Ok(())
+    }
+
+    /// Stop the current search.
+    ///
+    /// Everything the engine prints until its `bestmove` belongs to the old search, so it is
+    /// discarded here rather than being mistaken for output of the next one.
+    pub fn stop(&mut self) -> Result<()> {
+        if self.searching {
+            self.send("stop")?;
+            while !matches!(self.wait_for_message()?, UciMessage::BestMove { .. }) {}
+            self.searching = false;
+        }
+        Ok(())
+    }
+
+    /// Kill the engine process and start a fresh one with the same command line.
+    pub fn restart(&mut self) -> Result<()> {
+        let engine = Engine::spawn(&self.program, &self.args)?;
+        // The old engine is shut down when dropped
+        drop(std::mem::replace(self, engine));
+        Ok(())
+    }
+
+    /// Get the next message from the engine if one is available.
+    #[cfg(test)]
+    pub fn try_recv(&mut self) -> Result<Option<UciMessage>> {
+        match self.output.try_recv() {
+            Ok(message) => Ok(Some(self.track(message))),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => Err(Error::Engine(EngineKind::Terminated)),
+        }
+    }
+
+    /// Wait up to `timeout` for the next message from the engine.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<UciMessage>> {
+        match self.output.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(self.track(message))),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Engine(EngineKind::Terminated)),
+        }
+    }
+
+    fn track(&mut self, message: UciMessage) -> UciMessage {
+        if let UciMessage::BestMove { .. } = message {
+            self.searching = false;
+        }
+        message
+    }
+
+    fn wait_for_message(&mut self) -> Result<UciMessage> {
+        self.recv_timeout(RESPONSE_TIMEOUT)?
+            .ok_or(Error::Engine(EngineKind::Handshake))
+    }
+
+    fn send(&mut self, command: &str) -> Result<()> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| Error::Engine(EngineKind::Io))
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.process.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::common::{r#move::Move, square::Square};
+
+    use super::{uci::Score, *};
+
+    const FAKE_ENGINE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fake_engine.sh");
+
+    fn fake_engine() -> Engine {
+        Engine::spawn("sh", [FAKE_ENGINE]).expect("fake engine to start")
+    }
+
+    fn collect_until_bestmove(engine: &mut Engine) -> Vec<UciMessage> {
+        let mut messages = vec![];
+        loop {
+            let message = engine
+                .recv_timeout(RESPONSE_TIMEOUT)
+                .unwrap()
+                .expect("engine to answer");
+            let done = matches!(message, UciMessage::BestMove { .. });
+            messages.push(message);
+            if done {
+                return messages;
+            }
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        let engine = fake_engine();
+        assert_eq!(engine.name(), Some("Fake Engine"));
+    }
+
+    #[test]
+    fn spawn_missing_binary() {
+        assert_eq!(
+            Engine::spawn("./this-engine-does-not-exist", [""; 0]).err(),
+            Some(Error::Engine(EngineKind::Spawn))
+        );
+    }
+
+    #[test]
+    fn go_sends_board_fen() {
+        let mut engine = fake_engine();
+        let board =
+            Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        engine.go(&board, SearchLimit::Depth(2)).unwrap();
+        assert!(engine.is_searching());
+
+        let messages = collect_until_bestmove(&mut engine);
+        assert!(!engine.is_searching());
+        assert!(messages.contains(&UciMessage::Info(uci::Info {
+            string: Some(format!("position fen {board}")),
+            ..Default::default()
+        })));
+
+        let infos: Vec<&uci::Info> = messages
+            .iter()
+            .filter_map(|message| match message {
+                UciMessage::Info(info) if info.depth.is_some() => Some(info),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].score, Some(Score::Cp(25)));
+        assert_eq!(infos[1].score, Some(Score::Mate(3)));
+        assert_eq!(
+            infos[1].pv,
+            vec![
+                Move::new(Square::F1, Square::C4),
+                Move::new(Square::G8, Square::F6),
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_discards_stale_output() {
+        let mut engine = fake_engine();
+        engine.go(&Board::default(), SearchLimit::Infinite).unwrap();
+        engine.stop().unwrap();
+        assert!(!engine.is_searching());
+        assert_eq!(engine.try_recv(), Ok(None));
+    }
+
+    #[test]
+    fn restart() {
+        let mut engine = fake_engine();
+        engine.go(&Board::default(), SearchLimit::Infinite).unwrap();
+        engine.restart().unwrap();
+        assert!(!engine.is_searching());
+        assert_eq!(engine.name(), Some("Fake Engine"));
+
+        engine.go(&Board::default(), SearchLimit::Depth(1)).unwrap();
+        assert!(matches!(
+            collect_until_bestmove(&mut engine).last(),
+            Some(UciMessage::BestMove { .. })
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::common::r#move::Move;
+
+/// A line sent by a UCI engine on its standard output.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UciMessage {
+    Id(IdKind, String),
+    UciOk,
+    ReadyOk,
+    Info(Info),
+    BestMove { best: Move, ponder: Option<Move> },
+    Unknown(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IdKind {
+    Name,
+    Author,
+}
+
+/// Evaluation reported by the engine, always from the side to move's point of view.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Score {
+    /// Centipawns.
+    Cp(i32),
+    /// Mate in the given number of moves, negative when the side to move is getting mated.
+    Mate(i32),
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f32 / 100.),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+/// The structured content of an `info` line.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<u64>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+impl UciMessage {
+    /// Parse a single line of engine output.
+    ///
+    /// Lines that are not understood are kept as [`UciMessage::Unknown`] rather than rejected,
+    /// since engines are free to print anything they like.
+    pub fn parse(line: &str) -> Self {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("id") => match words.next() {
+                Some("name") => UciMessage::Id(IdKind::Name, words.collect::<Vec<_>>().join(" ")),
+                Some("author") => {
+                    UciMessage::Id(IdKind::Author, words.collect::<Vec<_>>().join(" "))
+                }
+                _ => UciMessage::Unknown(line.to_string()),
+            },
+            Some("uciok") => UciMessage::UciOk,
+            Some("readyok") => UciMessage::ReadyOk,
+            Some("info") => UciMessage::Info(Info::parse(words)),
+            Some("bestmove") => {
+                let best = words.next().and_then(|m| Move::from_str(m).ok());
+                let ponder = match (words.next(), words.next()) {
+                    (Some("ponder"), Some(m)) => Move::from_str(m).ok(),
+                    _ => None,
+                };
+                match best {
+                    Some(best) => UciMessage::BestMove { best, ponder },
+                    None => UciMessage::Unknown(line.to_string()),
+                }
+            }
+            _ => UciMessage::Unknown(line.to_string()),
+        }
+    }
+}
+
+impl Info {
+    fn parse<'a>(mut words: impl Iterator<Item = &'a str>) -> Self {
+        let mut info = Info::default();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => info.depth = words.next().and_then(|w| w.parse().ok()),
+                "seldepth" => info.seldepth = words.next().and_then(|w| w.parse().ok()),
+                "multipv" => info.multipv = words.next().and_then(|w| w.parse().ok()),
+                "nodes" => info.nodes = words.next().and_then(|w| w.parse().ok()),
+                "nps" => info.nps = words.next().and_then(|w| w.parse().ok()),
+                "time" => info.time = words.next().and_then(|w| w.parse().ok()),
+                "score" => {
+                    info.score = match (words.next(), words.next().and_then(|w| w.parse().ok())) {
+                        (Some("cp"), Some(cp)) => Some(Score::Cp(cp)),
+                        (Some("mate"), Some(moves)) => Some(Score::Mate(moves)),
+                        _ => None,
+                    }
+                }
+                // Both of these run until the end of the line
+                "pv" => {
+                    info.pv = words
+                        .by_ref()
+                        .map_while(|w| Move::from_str(w).ok())
+                        .collect();
+                }
+                "string" => {
+                    info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::square::Square;
+
+    use super::*;
+
+    #[test]
+    fn parses_id() {
+        assert_eq!(
+            UciMessage::parse("id name Stockfish 16"),
+            UciMessage::Id(IdKind::Name, "Stockfish 16".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_info_with_cp_score() {
+        let info = UciMessage::parse(
+            "info depth 12 seldepth 18 multipv 1 score cp 31 nodes 123456 nps 900000 time 137 pv e2e4 e7e5 g1f3",
+        );
+        assert_eq!(
+            info,
+            UciMessage::Info(Info {
+                depth: Some(12),
+                seldepth: Some(18),
+                multipv: Some(1),
+                score: Some(Score::Cp(31)),
+                nodes: Some(123456),
+                nps: Some(900000),
+                time: Some(137),
+                pv: vec![
+                    Move::new(Square::E2, Square::E4),
+                    Move::new(Square::E7, Square::E5),
+                    Move::new(Square::G1, Square::F3),
+                ],
+                string: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_info_with_mate_score() {
+        let UciMessage::Info(info) = UciMessage::parse("info depth 5 score mate -2 pv h7h8") else {
+            panic!("Expected info line");
+        };
+        assert_eq!(info.score, Some(Score::Mate(-2)));
+        assert_eq!(info.pv, vec![Move::new(Square::H7, Square::H8)]);
+    }
+
+    #[test]
+    fn parses_bestmove() {
+        assert_eq!(
+            UciMessage::parse("bestmove e2e4 ponder e7e5"),
+            UciMessage::BestMove {
+                best: Move::new(Square::E2, Square::E4),
+                ponder: Some(Move::new(Square::E7, Square::E5)),
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_lines() {
+        assert_eq!(
+            UciMessage::parse("Stockfish 16 by the Stockfish developers"),
+            UciMessage::Unknown("Stockfish 16 by the Stockfish developers".to_string())
+        );
+    }
+}
@@ -0,0 +1,90 @@
+/// Most states kept to undo, the oldest ones are forgotten first.
+const LIMIT: usize = 100;
+
+/// Earlier states of something being edited, to undo the edits and redo them.
+#[derive(Debug)]
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Remember `state`, the one before an edit. Edits undone before can't be redone anymore.
+    pub fn record(&mut self, state: T) {
+        if self.undo.len() == LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(state);
+        self.redo.clear();
+    }
+
+    /// The state before the last edit, `current` being kept to redo it.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// The state after the last edit undone, `current` being kept to undo it again.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::default();
+        assert_eq!(history.undo(0), None);
+
+        history.record(0);
+        history.record(1);
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), None);
+
+        // A new edit drops the ones undone
+        assert_eq!(history.undo(2), Some(1));
+        history.record(1);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(3), Some(1));
+    }
+
+    #[test]
+    fn forgets_oldest_states() {
+        let mut history = History::default();
+        for state in 0..=LIMIT {
+            history.record(state);
+        }
+        let mut current = LIMIT + 1;
+        while let Some(previous) = history.undo(current) {
+            current = previous;
+        }
+        assert_eq!(current, 1);
+    }
+}
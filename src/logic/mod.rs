@@ -1 +1,3 @@
+pub mod engine;
+pub mod history;
 pub mod movetree;
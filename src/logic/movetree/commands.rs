@@ -0,0 +1,272 @@
+//! Embedded command annotations, the `[%command ...]` found in PGN comments.
+//!
+//! Online platforms use them to record clock times (`%clk`), engine evaluations (`%eval`),
+//! coloured squares (`%csl`) and arrows (`%cal`).
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use crate::{common::square::Square, logic::engine::uci::Score};
+
+/// Colour of a marked square or arrow, written as its initial.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MarkColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl MarkColor {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'G' => Some(MarkColor::Green),
+            'R' => Some(MarkColor::Red),
+            'Y' => Some(MarkColor::Yellow),
+            'B' => Some(MarkColor::Blue),
+            _ => None,
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match self {
+            MarkColor::Green => 'G',
+            MarkColor::Red => 'R',
+            MarkColor::Yellow => 'Y',
+            MarkColor::Blue => 'B',
+        }
+    }
+}
+
+/// A square coloured with `%csl`, e.g. `Rd4`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SquareMark {
+    pub color: MarkColor,
+    pub square: Square,
+}
+
+/// An arrow drawn with `%cal`, e.g. `Ge2e4`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Arrow {
+    pub color: MarkColor,
+    pub from: Square,
+    pub to: Square,
+}
+
+impl FromStr for SquareMark {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let color = s.chars().next().and_then(MarkColor::from_char).ok_or(())?;
+        let square = Square::from_str(s.get(1..).ok_or(())?).map_err(|_| ())?;
+        Ok(SquareMark { color, square })
+    }
+}
+
+impl FromStr for Arrow {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 5 {
+            return Err(());
+        }
+        let color = s.chars().next().and_then(MarkColor::from_char).ok_or(())?;
+        let from = Square::from_str(s.get(1..3).ok_or(())?).map_err(|_| ())?;
+        let to = Square::from_str(s.get(3..5).ok_or(())?).map_err(|_| ())?;
+        Ok(Arrow { color, from, to })
+    }
+}
+
+impl Display for SquareMark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.color.as_char(), self.square)
+    }
+}
+
+impl Display for Arrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.color.as_char(), self.from, self.to)
+    }
+}
+
+/// The commands attached to a move.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Commands {
+    /// Time left on the clock of the player who moved.
+    pub clock: Option<Duration>,
+    /// Evaluation of the position, from White's point of view.
+    pub eval: Option<Score>,
+    pub squares: Vec<SquareMark>,
+    pub arrows: Vec<Arrow>,
+}
+
+impl Commands {
+    pub fn is_empty(&self) -> bool {
+        *self == Commands::default()
+    }
+
+    /// Move the commands out of `comments`, dropping the comments left empty.
+    pub fn take_from(&mut self, comments: &mut Vec<String>) {
+        for comment in comments.iter_mut() {
+            *comment = self.extract(comment);
+        }
+        comments.retain(|comment| !comment.is_empty());
+    }
+
+    /// Read the commands of `comment`, returning the text around them.
+    ///
+    /// Commands that aren't understood are left in the text.
+    pub fn extract(&mut self, comment: &str) -> String {
+        let mut text = String::new();
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let Some(end) = rest[start..].find(']').map(|end| start + end) else {
+                break;
+            };
+            text.push_str(&rest[..start]);
+            if !self.read(&rest[start + 2..end]) {
+                text.push_str(&rest[start..=end]);
+            }
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Read a single command, without its brackets. Returns whether it was understood.
+    fn read(&mut self, command: &str) -> bool {
+        let (name, value) = command.trim().split_once(' ').unwrap_or((command, ""));
+        let value = value.trim();
+        match name {
+            "clk" => match parse_clock(value) {
+                Some(clock) => self.clock = Some(clock),
+                None => return false,
+            },
+            "eval" => match parse_eval(value) {
+                Some(eval) => self.eval = Some(eval),
+                None => return false,
+            },
+            "csl" => match value.split(',').map(|mark| mark.trim().parse()).collect() {
+                Ok(squares) => self.squares.extend::<Vec<SquareMark>>(squares),
+                Err(()) => return false,
+            },
+            "cal" => match value.split(',').map(|mark| mark.trim().parse()).collect() {
+                Ok(arrows) => self.arrows.extend::<Vec<Arrow>>(arrows),
+                Err(()) => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// `h:mm:ss`, with an optional fraction of a second.
+fn parse_clock(value: &str) -> Option<Duration> {
+    let mut seconds = 0.;
+    for part in value.split(':') {
+        seconds = seconds * 60. + part.parse::<f64>().ok().filter(|n| *n >= 0.)?;
+    }
+    Some(Duration::from_millis((seconds * 1000.).round() as u64))
+}
+
+/// Pawns (`0.17`, `-1.5`) or a mate (`#3`, `#-2`).
+fn parse_eval(value: &str) -> Option<Score> {
+    // Some tools add the search depth after a comma
+    let value = value.split(',').next()?.trim();
+    match value.strip_prefix('#') {
+        Some(moves) => moves.parse().ok().map(Score::Mate),
+        None => value
+            .parse::<f64>()
+            .ok()
+            .filter(|pawns| pawns.is_finite())
+            .map(|pawns| Score::Cp((pawns * 100.).round() as i32)),
+    }
+}
+
+/// Clock time as `h:mm:ss`, with tenths of a second when there are some.
+pub fn format_clock(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    let tenths = clock.subsec_millis() / 100;
+    let time = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if tenths == 0 {
+        time
+    } else {
+        format!("{time}.{tenths}")
+    }
+}
+
+impl Display for Commands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut commands = vec![];
+        if let Some(eval) = self.eval {
+            commands.push(match eval {
+                Score::Cp(cp) => format!("[%eval {:.2}]", cp as f64 / 100.),
+                Score::Mate(moves) => format!("[%eval #{moves}]"),
+            });
+        }
+        if let Some(clock) = self.clock {
+            commands.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if !self.squares.is_empty() {
+            let squares: Vec<_> = self.squares.iter().map(ToString::to_string).collect();
+            commands.push(format!("[%csl {}]", squares.join(",")));
+        }
+        if !self.arrows.is_empty() {
+            let arrows: Vec<_> = self.arrows.iter().map(ToString::to_string).collect();
+            commands.push(format!("[%cal {}]", arrows.join(",")));
+        }
+        write!(f, "{}", commands.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_commands() {
+        let mut commands = Commands::default();
+        let text = commands
+            .extract("Nice [%eval -1.23] [%clk 0:03:05.2] [%csl Rd4,Ge5][%cal Ge2e4] [%foo]");
+        assert_eq!(text, "Nice [%foo]");
+        assert_eq!(commands.clock, Some(Duration::from_millis(185_200)));
+        assert_eq!(commands.eval, Some(Score::Cp(-123)));
+        assert_eq!(
+            commands.squares,
+            [
+                SquareMark {
+                    color: MarkColor::Red,
+                    square: Square::D4
+                },
+                SquareMark {
+                    color: MarkColor::Green,
+                    square: Square::E5
+                }
+            ]
+        );
+        assert_eq!(
+            commands.arrows,
+            [Arrow {
+                color: MarkColor::Green,
+                from: Square::E2,
+                to: Square::E4
+            }]
+        );
+        assert_eq!(
+            commands.to_string(),
+            "[%eval -1.23] [%clk 0:03:05.2] [%csl Rd4,Ge5] [%cal Ge2e4]"
+        );
+    }
+
+    #[test]
+    fn keeps_malformed_commands() {
+        let mut commands = Commands::default();
+        assert_eq!(commands.extract("[%clk soon] [%eval #-2]"), "[%clk soon]");
+        assert_eq!(commands.eval, Some(Score::Mate(-2)));
+        assert_eq!(commands.clock, None);
+    }
+}
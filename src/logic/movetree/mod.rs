@@ -1,13 +1,26 @@
+pub mod commands;
 pub mod pgn;
+pub mod tags;
 pub mod treenode;
 
+use std::str::FromStr;
+
 use indextree::{Arena, NodeId};
 
-use crate::{common::color::Color, error::Error, Result};
+use crate::{
+    common::{
+        board::Board, color::Color, piece::Piece, r#move::Move, square::ALL_SQUARES,
+        status::GameStatus,
+    },
+    error::Error,
+    Result,
+};
 
 use self::{
+    commands::{format_clock, Arrow, Commands, SquareMark},
     pgn::parser::STARTING_POSITION_FEN,
-    treenode::{CMove, Fen, Notation, TreeNode},
+    tags::Tags,
+    treenode::{comments_to_pgn, CMove, CResult, Fen, Nag, Notation, TreeNode},
 };
 
 #[derive(Clone, Debug)]
@@ -37,6 +50,8 @@ impl NextMoveOptions {
 pub struct MoveTree {
     tree: Arena<TreeNode>,
     game_start: NodeId,
+    root_fen: Fen,
+    tags: Tags,
 }
 
 impl Default for MoveTree {
@@ -48,9 +63,51 @@ impl Default for MoveTree {
 impl MoveTree {
     pub fn new() -> Self {
         let mut tree: indextree::Arena<TreeNode> = indextree::Arena::new();
-        let game_start = tree.new_node(TreeNode::GameStart);
+        let game_start = tree.new_node(TreeNode::GameStart(vec![], Commands::default()));
+
+        Self {
+            tree,
+            game_start,
+            root_fen: STARTING_POSITION_FEN.to_string(),
+            tags: Tags::default(),
+        }
+    }
 
-        Self { tree, game_start }
+    /// An empty tree for a game starting from the given position.
+    ///
+    /// The position is recorded in the `SetUp` and `FEN` tags, as the PGN standard requires.
+    pub fn with_root_fen(fen: &str) -> Result<Self> {
+        let mut move_tree = Self::new();
+        move_tree.set_root_fen(fen)?;
+        if move_tree.root_fen != STARTING_POSITION_FEN {
+            move_tree.tags.set("SetUp", "1");
+            move_tree.tags.set("FEN", &move_tree.root_fen.clone());
+        }
+        Ok(move_tree)
+    }
+
+    pub(crate) fn set_root_fen(&mut self, fen: &str) -> Result<()> {
+        let invalid = || Error::InvalidFen {
+            fen: fen.to_string(),
+        };
+        let board = Board::from_str(fen.trim()).map_err(|_| invalid())?;
+        // Everything downstream assumes both kings are on the board
+        for color in [Color::White, Color::Black] {
+            let kings = ALL_SQUARES
+                .into_iter()
+                .filter(|square| board.on_is(*square, (Piece::King, color)))
+                .count();
+            if kings != 1 {
+                return Err(invalid());
+            }
+        }
+        self.root_fen = board.to_string();
+        Ok(())
+    }
+
+    /// The position the game starts from.
+    pub fn root_fen(&self) -> &str {
+        &self.root_fen
     }
 
     pub fn add_new_move(&mut self, new_cmove: CMove, parent: NodeId, new_fen: String) -> NodeId {
@@ -59,44 +116,129 @@ impl MoveTree {
             parent
                 .children(&self.tree)
                 .find_map(|child| match self.tree[child].get() {
-                    TreeNode::StartVariation => {
+                    TreeNode::StartVariation(_) => {
                         let parent = self.tree[child].parent().unwrap();
                         match self.tree[parent].get() {
-                            TreeNode::Move(_, cmove) if *cmove == new_cmove => Some(parent),
+                            TreeNode::Move(_, cmove) if cmove.is_same_move(&new_cmove) => {
+                                Some(parent)
+                            }
                             _ => None,
                         }
                     }
-                    TreeNode::Move(_, cmove) if *cmove == new_cmove => Some(child),
+                    TreeNode::Move(_, cmove) if cmove.is_same_move(&new_cmove) => Some(child),
                     _ => None,
                 });
 
         match duplicate {
             Some(id) => id,
             None => {
-                if parent.children(&self.tree).next().is_some() {
-                    let start_variation = self.tree.new_node(TreeNode::StartVariation);
+                if self.mainline(parent).is_some() {
+                    let start_variation = self.tree.new_node(TreeNode::StartVariation(vec![]));
                     parent.append(start_variation, &mut self.tree);
                     let node = self.tree.new_node(TreeNode::Move(new_fen, new_cmove));
                     start_variation.append(node, &mut self.tree);
+                    let end_variation = self.tree.new_node(TreeNode::EndVariation);
+                    node.append(end_variation, &mut self.tree);
                     node
                 } else {
                     let node = self.tree.new_node(TreeNode::Move(new_fen, new_cmove));
                     parent.append(node, &mut self.tree);
+                    // The line, and the game when it is the mainline, now end with the new move
+                    for marker in self.line_end(parent) {
+                        marker.detach(&mut self.tree);
+                        node.append(marker, &mut self.tree);
+                    }
                     node
                 }
             }
         }
     }
 
+    /// Append the result of the game after `parent`, unless it already has one other than `*`.
+    ///
+    /// The result of the mainline is also written in the `Result` tag.
+    pub fn add_result(&mut self, result: CResult, parent: NodeId) -> NodeId {
+        let existing = parent
+            .children(&self.tree)
+            .find(|child| matches!(self.tree[*child].get(), TreeNode::Result(_)));
+        let node = match existing {
+            Some(id) if self.tree[id].get() != &TreeNode::Result(CResult::NoResult) => {
+                return id;
+            }
+            Some(id) => {
+                *self.tree[id].get_mut() = TreeNode::Result(result.clone());
+                id
+            }
+            None => {
+                let node = self.tree.new_node(TreeNode::Result(result.clone()));
+                parent.append(node, &mut self.tree);
+                node
+            }
+        };
+        if self.variation_of(parent).is_none() {
+            self.tags.set("Result", &result.to_string());
+        }
+        node
+    }
+
+    /// Add `nag` to the move at `node`, or remove it when the move already has it.
+    ///
+    /// A move keeps a single glyph of each kind, so `!` replaces `?`. Returns whether the
+    /// move changed.
+    pub fn toggle_nag(&mut self, node: NodeId, nag: Nag) -> bool {
+        let Some(TreeNode::Move(_, cmove)) = self.tree.get_mut(node).map(|node| node.get_mut())
+        else {
+            return false;
+        };
+        if let Some(index) = cmove.nags.iter().position(|other| *other == nag) {
+            cmove.nags.remove(index);
+        } else {
+            cmove.nags.retain(|other| !nag.excludes(*other));
+            cmove.nags.push(nag);
+            cmove.nags.sort();
+        }
+        true
+    }
+
+    /// Get the [`GameStatus`] after `node`, including repetitions of earlier positions.
+    pub fn status(&self, node: NodeId) -> GameStatus {
+        let Some(board) = self
+            .get_fen_for_node(node)
+            .and_then(|fen| Board::from_str(fen).ok())
+        else {
+            return GameStatus::Ongoing;
+        };
+
+        let status = board.status();
+        if status.is_over() {
+            return status;
+        }
+
+        // Same pieces, side to move, castling and en passant rights, move counters aside
+        let position = |fen: &str| fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+        let current = position(&board.to_string());
+        let repetitions = node
+            .ancestors(&self.tree)
+            .filter_map(|ancestor| self.get_fen_for_node(ancestor))
+            .filter(|fen| position(fen) == current)
+            .count();
+
+        if repetitions >= 3 {
+            GameStatus::ThreefoldRepetition
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
     pub fn get_prev_move(&self, id: NodeId) -> (NodeId, Fen) {
         match id.ancestors(&self.tree).nth(1) {
             Some(parent_id) => match self.tree[parent_id].get() {
-                TreeNode::GameStart => (self.game_start(), STARTING_POSITION_FEN.to_string()),
-                TreeNode::StartVariation => self.get_prev_move(parent_id),
+                TreeNode::GameStart(..) => (self.game_start(), self.root_fen.clone()),
+                TreeNode::StartVariation(_) => self.get_prev_move(parent_id),
                 TreeNode::Move(fen, _) => (parent_id, fen.to_string()),
                 TreeNode::EndVariation | TreeNode::Result(_) => unreachable!(),
             },
-            None => (self.game_start(), STARTING_POSITION_FEN.to_string()),
+            None => (self.game_start(), self.root_fen.clone()),
         }
     }
 
@@ -105,11 +247,11 @@ impl MoveTree {
             Vec::with_capacity(self.tree.capacity()),
             |mut acc, child| {
                 match self.tree[child].get() {
-                    TreeNode::StartVariation => acc.extend(self.get_next_move(child)),
+                    TreeNode::StartVariation(_) => acc.extend(self.get_next_move(child)),
                     TreeNode::Move(fen, cmove) => {
-                        acc.push((child, fen.to_string(), cmove.to_san()))
+                        acc.push((child, fen.to_string(), cmove.to_annotated_san()))
                     }
-                    TreeNode::EndVariation | TreeNode::GameStart | TreeNode::Result(_) => (),
+                    TreeNode::EndVariation | TreeNode::GameStart(..) | TreeNode::Result(_) => (),
                 }
                 acc
             },
@@ -120,59 +262,774 @@ impl MoveTree {
         self.game_start
     }
 
+    pub fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    /// The move played after `node` in its line, at the start of a variation its first move.
+    pub fn mainline(&self, node: NodeId) -> Option<NodeId> {
+        node.children(&self.tree)
+            .find(|child| matches!(self.tree[*child].get(), TreeNode::Move(..)))
+    }
+
+    /// The variations replacing the move played after `node`, each a
+    /// [`TreeNode::StartVariation`] to pass to [`MoveTree::mainline`].
+    pub fn variations(&self, node: NodeId) -> Vec<NodeId> {
+        node.children(&self.tree)
+            .filter(|child| matches!(self.tree[*child].get(), TreeNode::StartVariation(_)))
+            .collect()
+    }
+
+    /// The [`TreeNode::EndVariation`] and [`TreeNode::Result`] after `node`, which stay at the
+    /// end of its line when it is extended.
+    fn line_end(&self, node: NodeId) -> Vec<NodeId> {
+        node.children(&self.tree)
+            .filter(|child| {
+                matches!(
+                    self.tree[*child].get(),
+                    TreeNode::EndVariation | TreeNode::Result(_)
+                )
+            })
+            .collect()
+    }
+
+    /// The result recorded after `node`, when the game ends there.
+    pub fn result(&self, node: NodeId) -> Option<&CResult> {
+        node.children(&self.tree)
+            .find_map(|child| match self.tree[child].get() {
+                TreeNode::Result(result) => Some(result),
+                _ => None,
+            })
+    }
+
+    /// The move played at `node`, if it is a move.
+    pub fn get_move(&self, node: NodeId) -> Option<&CMove> {
+        match self.tree.get(node)?.get() {
+            TreeNode::Move(_, cmove) => Some(cmove),
+            _ => None,
+        }
+    }
+
+    /// Comments after the move at `node`, or before the first move of the game or variation.
+    pub fn comments(&self, node: NodeId) -> &[String] {
+        match self.tree.get(node).map(|node| node.get()) {
+            Some(TreeNode::Move(_, cmove)) => &cmove.comments_after,
+            Some(TreeNode::GameStart(comments, _) | TreeNode::StartVariation(comments)) => comments,
+            _ => &[],
+        }
+    }
+
+    /// Replace the comments returned by [`MoveTree::comments`]. Returns whether `node` can
+    /// hold comments.
+    pub fn set_comments(&mut self, node: NodeId, comments: Vec<String>) -> bool {
+        match self.tree.get_mut(node).map(|node| node.get_mut()) {
+            Some(TreeNode::Move(_, cmove)) => cmove.comments_after = comments,
+            Some(TreeNode::GameStart(existing, _) | TreeNode::StartVariation(existing)) => {
+                *existing = comments
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Comments before the move at `node`.
+    pub fn comments_before(&self, node: NodeId) -> &[String] {
+        self.get_move(node)
+            .map_or(&[], |cmove| cmove.comments_before.as_slice())
+    }
+
+    /// Replace the comments before the move at `node`. Returns whether `node` is a move.
+    pub fn set_comments_before(&mut self, node: NodeId, comments: Vec<String>) -> bool {
+        match self.tree.get_mut(node).map(|node| node.get_mut()) {
+            Some(TreeNode::Move(_, cmove)) => cmove.comments_before = comments,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Clock, evaluation and marks of the position at `node`, a move or the start of the game.
+    pub fn commands(&self, node: NodeId) -> Option<&Commands> {
+        match self.tree.get(node)?.get() {
+            TreeNode::Move(_, cmove) => Some(&cmove.commands),
+            TreeNode::GameStart(_, commands) => Some(commands),
+            _ => None,
+        }
+    }
+
+    fn commands_mut(&mut self, node: NodeId) -> Option<&mut Commands> {
+        match self.tree.get_mut(node)?.get_mut() {
+            TreeNode::Move(_, cmove) => Some(&mut cmove.commands),
+            TreeNode::GameStart(_, commands) => Some(commands),
+            _ => None,
+        }
+    }
+
+    /// Colour a square of the position at `node`, or clear it when it already has that colour.
+    ///
+    /// Returns whether the position changed.
+    pub fn toggle_square_mark(&mut self, node: NodeId, mark: SquareMark) -> bool {
+        let Some(commands) = self.commands_mut(node) else {
+            return false;
+        };
+        let existing = commands
+            .squares
+            .iter()
+            .position(|other| other.square == mark.square);
+        if let Some(index) = existing {
+            if commands.squares.remove(index) == mark {
+                return true;
+            }
+        }
+        commands.squares.push(mark);
+        true
+    }
+
+    /// Draw an arrow on the position at `node`, or clear it when it is already there in that
+    /// colour.
+    ///
+    /// Returns whether the position changed.
+    pub fn toggle_arrow(&mut self, node: NodeId, arrow: Arrow) -> bool {
+        let Some(commands) = self.commands_mut(node) else {
+            return false;
+        };
+        let existing = commands
+            .arrows
+            .iter()
+            .position(|other| other.from == arrow.from && other.to == arrow.to);
+        if let Some(index) = existing {
+            if commands.arrows.remove(index) == arrow {
+                return true;
+            }
+        }
+        commands.arrows.push(arrow);
+        true
+    }
+
+    /// Whether `node` is still in the tree, and not deleted with its line.
+    pub fn contains(&self, node: NodeId) -> bool {
+        self.tree.get(node).is_some() && !node.is_removed(&self.tree)
+    }
+
+    /// `node` and the nodes before it, back to the start of the game.
+    pub fn path(&self, node: NodeId) -> Vec<NodeId> {
+        node.ancestors(&self.tree).collect()
+    }
+
+    /// The [`TreeNode::StartVariation`] of the innermost variation `node` is in, if it isn't
+    /// on the mainline.
+    pub fn variation_of(&self, node: NodeId) -> Option<NodeId> {
+        node.ancestors(&self.tree)
+            .find(|ancestor| matches!(self.tree[*ancestor].get(), TreeNode::StartVariation(_)))
+    }
+
+    /// The last move of the line going through `node`.
+    fn end_of_line(&self, node: NodeId) -> NodeId {
+        let mut node = node;
+        while let Some(next) = self.mainline(node) {
+            node = next;
+        }
+        node
+    }
+
+    /// Swap the variation `node` is in with the line it branches from.
+    ///
+    /// Comments before the first move of either line go with it, the result of the game moves
+    /// to the end of the new mainline and the [`TreeNode::EndVariation`] to the end of the new
+    /// variation. Returns whether the tree changed.
+    pub fn promote_variation(&mut self, node: NodeId) -> bool {
+        let Some(variation) = self.variation_of(node) else {
+            return false;
+        };
+        let parent = self.tree[variation].parent().unwrap();
+        let (Some(first), Some(mainline)) = (self.mainline(variation), self.mainline(parent))
+        else {
+            return false;
+        };
+
+        // Comments before a variation are held by its start, and before a move by the move
+        let TreeNode::StartVariation(comments) = self.tree[variation].get_mut() else {
+            unreachable!()
+        };
+        let variation_comments = std::mem::take(comments);
+        if let TreeNode::Move(_, cmove) = self.tree[mainline].get_mut() {
+            let mainline_comments = std::mem::take(&mut cmove.comments_before);
+            if let TreeNode::StartVariation(comments) = self.tree[variation].get_mut() {
+                *comments = mainline_comments;
+            }
+        }
+        if let TreeNode::Move(_, cmove) = self.tree[first].get_mut() {
+            cmove.comments_before.splice(0..0, variation_comments);
+        }
+
+        // The result and the end of the variation stay at the end of the mainline and variation
+        let (old_end, new_end) = (self.end_of_line(mainline), self.end_of_line(first));
+        let (old_markers, new_markers) = (self.line_end(old_end), self.line_end(new_end));
+        for (marker, end) in old_markers
+            .into_iter()
+            .map(|marker| (marker, new_end))
+            .chain(new_markers.into_iter().map(|marker| (marker, old_end)))
+        {
+            marker.detach(&mut self.tree);
+            end.append(marker, &mut self.tree);
+        }
+
+        first.detach(&mut self.tree);
+        mainline.detach(&mut self.tree);
+        parent.prepend(first, &mut self.tree);
+        variation.append(mainline, &mut self.tree);
+        true
+    }
+
+    /// Move the variation `node` is in before the previous alternative to the same move.
+    ///
+    /// Returns whether the tree changed.
+    pub fn move_variation_up(&mut self, node: NodeId) -> bool {
+        let Some(variation) = self.variation_of(node) else {
+            return false;
+        };
+        let previous = variation
+            .preceding_siblings(&self.tree)
+            .skip(1)
+            .find(|sibling| matches!(self.tree[*sibling].get(), TreeNode::StartVariation(_)));
+        match previous {
+            Some(previous) => {
+                variation.detach(&mut self.tree);
+                previous.insert_before(variation, &mut self.tree);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the variation `node` is in after the next alternative to the same move.
+    ///
+    /// Returns whether the tree changed.
+    pub fn move_variation_down(&mut self, node: NodeId) -> bool {
+        let Some(variation) = self.variation_of(node) else {
+            return false;
+        };
+        let next = variation
+            .following_siblings(&self.tree)
+            .skip(1)
+            .find(|sibling| matches!(self.tree[*sibling].get(), TreeNode::StartVariation(_)));
+        match next {
+            Some(next) => {
+                variation.detach(&mut self.tree);
+                next.insert_after(variation, &mut self.tree);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delete the variation `node` is in, with the variations inside it.
+    ///
+    /// Returns whether the tree changed.
+    pub fn delete_variation(&mut self, node: NodeId) -> bool {
+        match self.variation_of(node) {
+            Some(variation) => {
+                variation.remove_subtree(&mut self.tree);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delete everything played after `node`: the rest of its line and the variations
+    /// replacing the next move.
+    ///
+    /// The line now ends at `node`, with the result of the game when it is the mainline.
+    /// Returns whether the tree changed.
+    pub fn delete_after(&mut self, node: NodeId) -> bool {
+        let markers = self.line_end(self.end_of_line(node));
+        for marker in &markers {
+            marker.detach(&mut self.tree);
+        }
+        let children: Vec<_> = node.children(&self.tree).collect();
+        for child in &children {
+            child.remove_subtree(&mut self.tree);
+        }
+        for marker in markers {
+            node.append(marker, &mut self.tree);
+        }
+        !children.is_empty()
+    }
+
+    /// Delete every variation, keeping only the mainline.
+    ///
+    /// Returns whether the tree changed.
+    pub fn strip_variations(&mut self) -> bool {
+        let mut variations = vec![];
+        let mut node = self.game_start;
+        while let Some(next) = self.mainline(node) {
+            variations.extend(self.variations(node));
+            node = next;
+        }
+        for variation in &variations {
+            variation.remove_subtree(&mut self.tree);
+        }
+        !variations.is_empty()
+    }
+
+    /// The move that reached `node`, found by replaying it from the previous position.
+    pub fn last_move(&self, node: NodeId) -> Option<Move> {
+        let TreeNode::Move(fen, _) = self.tree.get(node)?.get() else {
+            return None;
+        };
+        let (_, previous) = self.get_prev_move(node);
+        let board = Board::from_str(&previous).ok()?;
+        let position = |fen: &str| fen.split(' ').take(4).collect::<Vec<_>>().join(" ");
+        let target = position(fen);
+        board.legal_moves().into_iter().find(|m| {
+            let mut next = board;
+            position(&next.update(*m).to_string()) == target
+        })
+    }
+
     pub fn get_fen_for_node(&self, id: NodeId) -> Option<&str> {
         match self.tree[id].get() {
+            TreeNode::GameStart(..) => Some(&self.root_fen),
             TreeNode::Move(fen, _) => Some(fen),
             _ => None,
         }
     }
 
-    pub fn generate_pgn_for_node(&self, root: NodeId) -> String {
-        match self.tree[root].get() {
-            TreeNode::Move(_, cmove) => match cmove.color {
-                Color::White => format!("{}. {}", cmove.move_number, &cmove.to_san()),
-                Color::Black => {
-                    let parent = self.tree[root].parent().unwrap();
-                    if matches!(self.tree[parent].get(), TreeNode::StartVariation) {
-                        format!("{}... {} ", cmove.move_number, cmove.to_san())
-                    } else {
-                        format!(" {} ", cmove.to_san())
-                    }
-                }
-            },
-            _ => self.tree[root].get().to_string(),
-        }
+    /// Movetext of `root` and every move after it, with NAG symbols instead of `$n`.
+    pub fn generate_move_text(&self, root: NodeId) -> String {
+        self.generate(root, true)
     }
+
     pub fn generate_pgn(&self, root: NodeId) -> String {
         let mut pgn = String::new();
 
-        // First append root's PGN to string
+        // The tag pair section goes before the movetext of the whole game
+        if root == self.game_start && !self.tags.is_empty() {
+            pgn.push_str(&format!("{}\n", self.tags));
+        }
+        pgn.push_str(&self.generate(root, false));
+        pgn
+    }
 
-        pgn.push_str(&self.generate_pgn_for_node(root));
+    pub fn generate_pgn_for_node(&self, root: NodeId, symbols: bool) -> String {
+        let TreeNode::Move(_, cmove) = self.tree[root].get() else {
+            return match self.tree[root].get() {
+                // Marks are drawn on the board rather than listed with the moves
+                TreeNode::GameStart(comments, _) if symbols => comments_to_pgn(comments),
+                node => node.to_string(),
+            };
+        };
+        let parent = self.tree[root].parent().unwrap();
+        // Black moves only need their number when something comes between them and White's
+        let numbered = cmove.color == Color::White
+            || !cmove.comments_before.is_empty()
+            || match self.tree[parent].get() {
+                TreeNode::Move(_, previous) => {
+                    !previous.comments_after.is_empty()
+                        || !previous.commands.is_empty()
+                        || self.has_variations(parent)
+                }
+                _ => true,
+            };
 
-        if let Some(mainline) = root
-            .children(&self.tree)
-            .find(|child| matches!(self.tree[*child].get(), TreeNode::Move(..)))
-        {
-            // Then append mainline child
-            pgn.push_str(&self.generate_pgn_for_node(mainline));
-
-            // Then go through all variation nodes
-            // Start variation appends a (
-            // Manually append a )
-            root.children(&self.tree)
-                .filter(|child| matches!(self.tree[*child].get(), TreeNode::StartVariation))
-                .for_each(|variation| {
-                    pgn.push_str(&format!(" {} ", &self.generate_pgn(variation)));
-                    pgn.push_str(" )");
-                });
+        let mut pgn = vec![];
+        if !cmove.comments_before.is_empty() {
+            pgn.push(comments_to_pgn(&cmove.comments_before));
+        }
+        match (numbered, cmove.color) {
+            (true, Color::White) => pgn.push(format!("{}.", cmove.move_number)),
+            (true, Color::Black) => pgn.push(format!("{}...", cmove.move_number)),
+            (false, _) => (),
+        }
+        pgn.push(cmove.notation(symbols));
+        match (symbols, cmove.commands.clock) {
+            // The move list only shows the clock out of the commands
+            (true, Some(clock)) => pgn.push(format!("[{}]", format_clock(clock))),
+            (true, None) => (),
+            (false, _) if cmove.commands.is_empty() => (),
+            (false, _) => pgn.push(format!("{{{}}}", cmove.commands)),
+        }
+        if !cmove.comments_after.is_empty() {
+            pgn.push(comments_to_pgn(&cmove.comments_after));
+        }
+        pgn.join(" ")
+    }
 
-            // Then append rest of mainline's children
-            mainline
-                .children(&self.tree)
-                .for_each(|variation| pgn.push_str(&format!(" {}", &self.generate_pgn(variation))));
+    /// Whether alternatives to the move at `node` were played.
+    fn has_variations(&self, node: NodeId) -> bool {
+        node.preceding_siblings(&self.tree)
+            .chain(node.following_siblings(&self.tree))
+            .any(|sibling| matches!(self.tree[sibling].get(), TreeNode::StartVariation(_)))
+    }
+
+    fn generate(&self, root: NodeId, symbols: bool) -> String {
+        let mut pgn = vec![self.generate_pgn_for_node(root, symbols)];
+        let mut node = root;
+
+        // Follow the mainline, each move followed by the variations replacing it
+        while let Some(mainline) = self.mainline(node) {
+            pgn.push(self.generate_pgn_for_node(mainline, symbols));
+            for variation in self.variations(node) {
+                pgn.push(format!("({})", self.generate(variation, symbols)));
+            }
+            node = mainline;
+        }
+
+        // The game ends here, variations can't hold a result
+        if !matches!(self.tree[root].get(), TreeNode::StartVariation(_)) {
+            if let Some(result) = self.result(node) {
+                pgn.push(result.to_string());
+            }
+        }
+
+        pgn.retain(|text| !text.is_empty());
+        pgn.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::square::Square,
+        logic::movetree::{
+            commands::MarkColor,
+            pgn::{lexer::tokenize, parser::PgnParser},
+        },
+    };
+
+    use super::*;
+
+    fn last_mainline_move(move_tree: &MoveTree) -> NodeId {
+        let mut node = move_tree.game_start();
+        while let Some((next, _, _)) = move_tree.get_next_move(node).first() {
+            node = *next;
+        }
+        node
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let tokens = tokenize("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 *");
+        let move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+        let last = last_mainline_move(&move_tree);
+
+        assert_eq!(move_tree.status(last), GameStatus::ThreefoldRepetition);
+        // The draw has to be claimed
+        assert_eq!(move_tree.status(last).result(), None);
+        let (before_last, _) = move_tree.get_prev_move(last);
+        assert_eq!(move_tree.status(before_last), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn add_result_once() {
+        let mut move_tree = MoveTree::new();
+        let mut node = move_tree.game_start();
+        let mut board = Board::default();
+        for m in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            let m = crate::common::r#move::Move::from_str(m).unwrap();
+            let cmove = m.try_into_cmove(board).unwrap();
+            board.update(m);
+            node = move_tree.add_new_move(cmove, node, board.to_string());
+        }
+        assert_eq!(move_tree.status(node), GameStatus::Checkmate(Color::Black));
+        assert!(move_tree
+            .generate_pgn(move_tree.game_start())
+            .contains("Qh4#"));
+
+        let result = move_tree.add_result(CResult::BlackWins, node);
+        assert_eq!(move_tree.add_result(CResult::BlackWins, node), result);
+        assert!(move_tree
+            .generate_pgn(move_tree.game_start())
+            .trim_end()
+            .ends_with("0-1"));
+        assert_eq!(move_tree.tags().result.as_deref(), Some("0-1"));
+    }
+
+    #[test]
+    fn marks_are_exported() {
+        let tokens = tokenize("1. e4 *");
+        let mut move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+        let start = move_tree.game_start();
+        let e4 = last_mainline_move(&move_tree);
+        let arrow = |color| Arrow {
+            color,
+            from: Square::G1,
+            to: Square::F3,
+        };
+        let mark = SquareMark {
+            color: MarkColor::Red,
+            square: Square::E5,
         };
 
-        pgn
+        assert!(move_tree.toggle_arrow(e4, arrow(MarkColor::Green)));
+        assert!(move_tree.toggle_square_mark(e4, mark));
+        assert!(move_tree.toggle_square_mark(start, mark));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "{[%csl Re5]} 1. e4 {[%csl Re5] [%cal Gg1f3]} *"
+        );
+
+        // Another colour replaces the arrow, the same one removes it
+        move_tree.toggle_arrow(e4, arrow(MarkColor::Blue));
+        assert_eq!(
+            move_tree.commands(e4).unwrap().arrows,
+            [arrow(MarkColor::Blue)]
+        );
+        move_tree.toggle_arrow(e4, arrow(MarkColor::Blue));
+        move_tree.toggle_square_mark(e4, mark);
+        assert!(move_tree.commands(e4).unwrap().is_empty());
+
+        let tokens = tokenize(&move_tree.generate_pgn(start));
+        assert_eq!(PgnParser::new(tokens.iter()).parse().unwrap(), move_tree);
+    }
+
+    #[test]
+    fn last_move() {
+        let tokens = tokenize("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. 0-0 *");
+        let move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+        let castle = last_mainline_move(&move_tree);
+
+        assert_eq!(
+            move_tree.last_move(castle),
+            Some(Move::new(Square::E1, Square::G1))
+        );
+        assert_eq!(move_tree.last_move(move_tree.game_start()), None);
+    }
+
+    #[test]
+    fn comments_around_moves() {
+        let mut move_tree = parse("{Start} 1. e4 {Best} e5 *");
+        let start = move_tree.game_start();
+        let e4 = find(&move_tree, "e4");
+        let e5 = find(&move_tree, "e5");
+
+        assert!(move_tree.set_comments_before(e5, vec!["Symmetric".to_string()]));
+        assert!(move_tree.set_comments(e5, vec!["Solid".to_string()]));
+        assert_eq!(move_tree.comments_before(e5), ["Symmetric"]);
+        assert!(move_tree.comments_before(e4).is_empty());
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "{Start} 1. e4 {Best} {Symmetric} 1... e5 {Solid} *"
+        );
+
+        // The start of the game only has the comments before its first move
+        assert!(!move_tree.set_comments_before(start, vec![]));
+        assert_eq!(move_tree.comments(start), ["Start"]);
+    }
+
+    #[test]
+    fn toggle_nag() {
+        let tokens = tokenize("1. e4 *");
+        let mut move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+        let e4 = last_mainline_move(&move_tree);
+        let nags = |move_tree: &MoveTree| move_tree.get_move(e4).unwrap().nags.clone();
+
+        assert!(move_tree.toggle_nag(e4, Nag::GOOD));
+        assert!(move_tree.toggle_nag(e4, Nag::WHITE_ADVANTAGE));
+        assert_eq!(nags(&move_tree), vec![Nag::GOOD, Nag::WHITE_ADVANTAGE]);
+
+        // A single move assessment at a time
+        move_tree.toggle_nag(e4, Nag::BLUNDER);
+        assert_eq!(nags(&move_tree), vec![Nag::BLUNDER, Nag::WHITE_ADVANTAGE]);
+
+        move_tree.toggle_nag(e4, Nag::WHITE_ADVANTAGE);
+        assert_eq!(nags(&move_tree), vec![Nag::BLUNDER]);
+
+        assert!(!move_tree.toggle_nag(move_tree.game_start(), Nag::GOOD));
+    }
+
+    fn parse(pgn: &str) -> MoveTree {
+        let tokens = tokenize(pgn);
+        PgnParser::new(tokens.iter()).parse().unwrap()
+    }
+
+    /// The node of the move written `san` that comes first in the tree.
+    fn find(move_tree: &MoveTree, san: &str) -> NodeId {
+        move_tree
+            .game_start()
+            .descendants(&move_tree.tree)
+            .find(|node| move_tree.get_move(*node).is_some_and(|m| m.to_san() == san))
+            .unwrap()
+    }
+
+    /// The [`TreeNode::EndVariation`] after `node`, when a variation ends there.
+    fn end_variation(move_tree: &MoveTree, node: NodeId) -> Option<NodeId> {
+        node.children(&move_tree.tree)
+            .find(|child| matches!(move_tree.tree[*child].get(), TreeNode::EndVariation))
+    }
+
+    /// Check every variation ends with a single [`TreeNode::EndVariation`] after its last move.
+    fn assert_variations_end(move_tree: &MoveTree) {
+        let nodes: Vec<_> = move_tree
+            .game_start()
+            .descendants(&move_tree.tree)
+            .collect();
+        let count = |kind: fn(&TreeNode) -> bool| {
+            nodes
+                .iter()
+                .filter(|node| kind(move_tree.tree[**node].get()))
+                .count()
+        };
+        assert_eq!(
+            count(|node| matches!(node, TreeNode::StartVariation(_))),
+            count(|node| matches!(node, TreeNode::EndVariation))
+        );
+        for variation in nodes
+            .iter()
+            .filter(|node| matches!(move_tree.tree[**node].get(), TreeNode::StartVariation(_)))
+        {
+            let end = move_tree.end_of_line(*variation);
+            assert!(end_variation(move_tree, end).is_some());
+        }
+    }
+
+    #[test]
+    fn continue_loaded_game() {
+        let mut move_tree = parse("1. e4 e5 *");
+        let e5 = find(&move_tree, "e5");
+        let fen = move_tree.get_fen_for_node(e5).unwrap().to_string();
+        let mut board = Board::from_str(&fen).unwrap();
+        let m = Move::new(Square::G1, Square::F3);
+        let cmove = m.try_into_cmove(board).unwrap();
+        board.update(m);
+
+        let nf3 = move_tree.add_new_move(cmove, e5, board.to_string());
+        assert_eq!(move_tree.mainline(e5), Some(nf3));
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 2. Nf3 *"
+        );
+    }
+
+    #[test]
+    fn result_replaces_no_result() {
+        let mut move_tree = parse("[Result \"*\"]\n\n1. f3 e5 2. g4 *");
+        let g4 = find(&move_tree, "g4");
+        let fen = move_tree.get_fen_for_node(g4).unwrap().to_string();
+        let mut board = Board::from_str(&fen).unwrap();
+        let m = Move::new(Square::D8, Square::H4);
+        let cmove = m.try_into_cmove(board).unwrap();
+        board.update(m);
+
+        let qh4 = move_tree.add_new_move(cmove, g4, board.to_string());
+        let result = move_tree.status(qh4).result().unwrap();
+        move_tree.add_result(result, qh4);
+        assert_eq!(move_tree.result(qh4), Some(&CResult::BlackWins));
+        assert_eq!(move_tree.tags().result.as_deref(), Some("0-1"));
+        assert!(move_tree
+            .generate_pgn(move_tree.game_start())
+            .ends_with("[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1"));
+    }
+
+    #[test]
+    fn en_passant_round_trip() {
+        let mut move_tree = parse("1. e4 a6 2. e5 d5 *");
+        let d5 = find(&move_tree, "d5");
+        let fen = move_tree.get_fen_for_node(d5).unwrap().to_string();
+        let mut board = Board::from_str(&fen).unwrap();
+        let m = Move::new(Square::E5, Square::D6);
+        let cmove = m.try_into_cmove(board).unwrap();
+        board.update(m);
+        move_tree.add_new_move(cmove, d5, board.to_string());
+
+        let pgn = move_tree.generate_pgn(move_tree.game_start());
+        assert_eq!(pgn, "1. e4 a6 2. e5 d5 3. exd6 *");
+        let reloaded = parse(&pgn);
+        assert_eq!(reloaded.generate_pgn(reloaded.game_start()), pgn);
+    }
+
+    #[test]
+    fn extend_variation() {
+        let mut move_tree = parse("1. e4 e5 (1... c5) *");
+        let c5 = find(&move_tree, "c5");
+        let fen = move_tree.get_fen_for_node(c5).unwrap().to_string();
+        let mut board = Board::from_str(&fen).unwrap();
+        let m = Move::new(Square::G1, Square::F3);
+        let cmove = m.try_into_cmove(board).unwrap();
+        board.update(m);
+
+        let nf3 = move_tree.add_new_move(cmove, c5, board.to_string());
+        assert_variations_end(&move_tree);
+        assert!(end_variation(&move_tree, nf3).is_some());
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 (1... c5 2. Nf3) *"
+        );
+    }
+
+    #[test]
+    fn promote_variation() {
+        let mut move_tree = parse("1. e4 e5 ({Sicilian} 1... c5 2. Nf3 (2. c3)) 2. Nf3 1-0");
+        let start = move_tree.game_start();
+
+        assert!(move_tree.promote_variation(find(&move_tree, "c3")));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 e5 ({Sicilian} 1... c5 2. c3 (2. Nf3)) 2. Nf3 1-0"
+        );
+        assert_variations_end(&move_tree);
+
+        assert!(move_tree.promote_variation(find(&move_tree, "c5")));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 {Sicilian} 1... c5 (1... e5 2. Nf3) 2. c3 (2. Nf3) 1-0"
+        );
+        assert_variations_end(&move_tree);
+
+        assert!(!move_tree.promote_variation(find(&move_tree, "e4")));
+    }
+
+    #[test]
+    fn move_variations() {
+        let mut move_tree = parse("1. e4 e5 (1... c5) (1... e6) (1... d5) *");
+        let start = move_tree.game_start();
+
+        assert!(move_tree.move_variation_up(find(&move_tree, "e6")));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 e5 (1... e6) (1... c5) (1... d5) *"
+        );
+        assert!(!move_tree.move_variation_up(find(&move_tree, "e6")));
+
+        assert!(move_tree.move_variation_down(find(&move_tree, "c5")));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 e5 (1... e6) (1... d5) (1... c5) *"
+        );
+        assert!(!move_tree.move_variation_down(find(&move_tree, "c5")));
+        assert!(!move_tree.move_variation_down(find(&move_tree, "e5")));
+    }
+
+    #[test]
+    fn delete_lines() {
+        let mut move_tree =
+            parse("1. e4 e5 (1... c5 2. Nf3 (2. c3)) 2. Nf3 Nc6 (2... d6) 3. Bb5 *");
+        let start = move_tree.game_start();
+
+        let c3 = find(&move_tree, "c3");
+        assert!(move_tree.delete_variation(c3));
+        assert!(!move_tree.contains(c3));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 (2... d6) 3. Bb5 *"
+        );
+        assert!(!move_tree.delete_variation(find(&move_tree, "e5")));
+
+        assert!(move_tree.delete_after(find(&move_tree, "Nc6")));
+        assert_eq!(
+            move_tree.generate_pgn(start),
+            "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 (2... d6) *"
+        );
+        assert!(move_tree.delete_after(find(&move_tree, "c5")));
+        let pgn = move_tree.generate_pgn(start);
+        assert_eq!(pgn, "1. e4 e5 (1... c5) 2. Nf3 Nc6 (2... d6) *");
+        assert_variations_end(&move_tree);
+        let reloaded = parse(&pgn);
+        assert_eq!(reloaded.generate_pgn(reloaded.game_start()), pgn);
+        assert!(!move_tree.delete_after(find(&move_tree, "c5")));
+        assert!(!move_tree.delete_after(find(&move_tree, "Nc6")));
+
+        assert!(move_tree.strip_variations());
+        assert_eq!(move_tree.generate_pgn(start), "1. e4 e5 2. Nf3 Nc6 *");
+        assert!(!move_tree.strip_variations());
     }
 }
@@ -0,0 +1,251 @@
+use crate::logic::movetree::{tags::Tags, MoveTree};
+
+use super::{
+    errors::PgnParseError,
+    lexer::{tokenize_with_spans, Span},
+    parser::PgnParser,
+};
+
+/// One game of a PGN database, kept even when its movetext could not be parsed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GameEntry {
+    /// The game as it was written in the database.
+    pub text: String,
+    pub tags: Tags,
+    pub game: Result<MoveTree, PgnParseError>,
+    /// What was skipped to read the game when it isn't valid PGN.
+    pub warnings: Vec<PgnParseError>,
+}
+
+/// Parse every game of a PGN database.
+#[cfg(test)]
+pub fn parse_games(input: &str) -> Vec<GameEntry> {
+    games(input).collect()
+}
+
+/// Parse the games of a PGN database as they are iterated.
+///
+/// A game that fails to parse does not affect the games around it. When the game isn't valid
+/// PGN, the moves that can be read are kept and the rest is reported as warnings. Errors are
+/// located in the whole database.
+pub fn games(input: &str) -> impl Iterator<Item = GameEntry> + '_ {
+    // The games follow each other from the start of the database
+    split_games(input).scan(0, move |offset, text| {
+        let game = parse_game(input, text, *offset);
+        *offset += text.len();
+        Some(game)
+    })
+}
+
+/// Parse the game `text`, which starts at `offset` in the database `input`.
+fn parse_game(input: &str, text: &str, offset: usize) -> GameEntry {
+    let error = match PgnParser::from_text(text).parse() {
+        Ok(move_tree) => {
+            return GameEntry {
+                text: text.to_string(),
+                tags: move_tree.tags().clone(),
+                game: Ok(move_tree),
+                warnings: vec![],
+            }
+        }
+        Err(error) => error,
+    };
+
+    // The game is read again leniently, from tokens that know where they are to locate what
+    // went wrong
+    let (tokens, spans) = tokenize_with_spans(text);
+    let spans: Vec<_> = spans
+        .into_iter()
+        .map(|span| Span {
+            start: offset + span.start,
+            end: offset + span.end,
+        })
+        .collect();
+    let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+    // Nothing worth showing could be read
+    let (game, warnings) = if move_tree.mainline(move_tree.game_start()).is_none() {
+        (Err(error.locate(input, &spans)), vec![])
+    } else {
+        let warnings = warnings
+            .into_iter()
+            .map(|warning| warning.locate(input, &spans))
+            .collect();
+        (Ok(move_tree), warnings)
+    };
+
+    let tags = match &game {
+        Ok(move_tree) => move_tree.tags().clone(),
+        Err(_) => PgnParser::new(tokens.iter()).parse_tags(),
+    };
+    GameEntry {
+        text: text.to_string(),
+        tags,
+        game,
+        warnings,
+    }
+}
+
+/// Split a PGN database into the text of each game, as they are iterated.
+///
+/// A game ends where the tag pair section of the next one starts, i.e. at the first line
+/// starting with '[' after some movetext. Comments are skipped, so a '[' inside one
+/// doesn't cut a game in half, and so are braces in tag values and `%` escape lines.
+pub fn split_games(input: &str) -> SplitGames<'_> {
+    SplitGames { input, start: 0 }
+}
+
+/// The text of each game of a PGN database, see [`split_games`].
+#[derive(Debug, Clone)]
+pub struct SplitGames<'a> {
+    input: &'a str,
+    /// Where the next game starts.
+    start: usize,
+}
+
+impl<'a> Iterator for SplitGames<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.start..];
+        if rest.trim().is_empty() {
+            self.start = self.input.len();
+            return None;
+        }
+
+        let mut offset = 0;
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        for line in rest.split_inclusive('\n') {
+            // An escape line is skipped whole, it can't open or close a comment
+            if !in_comment && line.starts_with('%') {
+                offset += line.len();
+                continue;
+            }
+
+            let trimmed = line.trim_start();
+            if !in_comment && !trimmed.is_empty() {
+                if !trimmed.starts_with('[') {
+                    in_movetext = true;
+                } else if in_movetext {
+                    self.start += offset;
+                    return Some(&rest[..offset]);
+                }
+            }
+
+            // Strings don't go past the end of their tag pair
+            let mut in_string = false;
+            let mut chars = line.chars();
+            while let Some(char) = chars.next() {
+                match char {
+                    '\\' if in_string => {
+                        chars.next();
+                    }
+                    '"' if !in_comment => in_string = !in_string,
+                    _ if in_string => {}
+                    '{' => in_comment = true,
+                    '}' => in_comment = false,
+                    // Rest of line comment
+                    ';' if !in_comment => break,
+                    _ => {}
+                }
+            }
+            offset += line.len();
+        }
+
+        self.start = self.input.len();
+        Some(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATABASE: &str = r#"[Event "Club championship"]
+[White "Alice"]
+[Black "Bob"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event "Club championship"]
+[White "Carol"]
+[Black "Dave"]
+[Result "*"]
+
+1. e4 {A comment
+[that looks like a tag]} e5 2. Kz9 *
+
+[Event "Club championship"]
+[White "Gina"]
+[Black "Hank"]
+
+1. Kz9 e5 *
+
+[Event "Club championship"]
+[White "Erin"]
+[Black "Frank"]
+[Result "1/2-1/2"]
+
+1. d4 d5 1/2-1/2
+"#;
+
+    #[test]
+    fn splits_games() {
+        let games: Vec<_> = split_games(DATABASE).collect();
+        assert_eq!(games.len(), 4);
+        assert!(games[1].contains("[that looks like a tag]"));
+        assert!(games[2].starts_with("[Event"));
+        assert!(games[3].starts_with("[Event"));
+        assert_eq!(games.concat(), DATABASE);
+    }
+
+    #[test]
+    fn braces_in_tags_and_escapes() {
+        let input = r#"[Event "Open {A"]
+[Site "\"}\" Club"]
+
+1. e4 *
+
+% Exported {by hand
+[Event "Open B}"]
+
+1. d4 *
+"#;
+        let games: Vec<_> = split_games(input).collect();
+        assert_eq!(games.len(), 2);
+        assert!(games[1].starts_with("[Event \"Open B}\"]"));
+    }
+
+    #[test]
+    fn keeps_going_after_a_bad_game() {
+        let games = parse_games(DATABASE);
+        assert_eq!(games.len(), 4);
+
+        assert!(games[0].game.is_ok());
+        assert!(games[0].warnings.is_empty());
+        assert_eq!(games[0].tags.white.as_deref(), Some("Alice"));
+
+        // The moves before the bad one are kept
+        let move_tree = games[1].game.as_ref().unwrap();
+        assert!(move_tree
+            .generate_pgn(move_tree.game_start())
+            .ends_with("[that looks like a tag]} 1... e5 *"));
+        assert_eq!(games[1].warnings.len(), 1);
+        let error = &games[1].warnings[0];
+        let location = error.location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (14, 29));
+        assert_eq!(location.snippet, "[that looks like a tag]} e5 2. Kz9 *");
+        assert!(error
+            .to_string()
+            .ends_with(&format!("\n{}^", " ".repeat(28))));
+        assert_eq!(games[1].tags.white.as_deref(), Some("Carol"));
+
+        let error = games[2].game.as_ref().unwrap_err();
+        assert_eq!(error.location.as_ref().unwrap().line, 20);
+        assert_eq!(games[2].tags.white.as_deref(), Some("Gina"));
+
+        assert!(games[3].game.is_ok());
+        assert_eq!(games[3].tags.result.as_deref(), Some("1/2-1/2"));
+    }
+}
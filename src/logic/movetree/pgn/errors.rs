@@ -1,14 +1,77 @@
 use std::fmt::Display;
 
-#[derive(PartialEq, Eq, Debug)]
+use crate::{common::color::Color, logic::movetree::treenode::CMove};
+
+use super::lexer::Span;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub struct PgnParseError {
+    /// Index of the token the error is at.
     pub index: usize,
+    pub kind: ErrorKind,
     pub message: String,
+    /// Where the error is in the source, once [located](PgnParseError::locate).
+    pub location: Option<Location>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ErrorKind {
+    /// The text isn't valid PGN.
+    Syntax,
+    /// A move can't be played in the position it is read in.
+    Move {
+        error: MoveError,
+        move_number: usize,
+        color: Color,
+    },
+}
+
+/// Why a move doesn't fit the position it is played in.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MoveError {
+    /// No piece of that kind can go to the square.
+    Impossible,
+    /// The move would leave the king in check, or castle without the right to.
+    Illegal,
+    /// Several pieces can go to the square and the move doesn't say which.
+    Ambiguous,
+    /// The move is marked with the wrong check or mate suffix, `check` and `checkmate`
+    /// being what it really gives.
+    WrongSuffix { check: bool, checkmate: bool },
+    /// The move is marked as a capture when it isn't one or the other way around, `captures`
+    /// being whether it really takes a piece.
+    WrongCapture { captures: bool },
+}
+
+/// Where a parse error is in the source.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Location {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Character on the line, starting at 1.
+    pub column: usize,
+    /// The line the error is on.
+    pub snippet: String,
 }
 
 impl Display for PgnParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.message)?;
+        if let Some(location) = &self.location {
+            // Point at the error under its line, tabs kept to line up with it
+            let padding: String = location
+                .snippet
+                .chars()
+                .take(location.column - 1)
+                .map(|char| if char == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(
+                f,
+                "\nLine {}, column {}:\n{}\n{padding}^",
+                location.line, location.column, location.snippet
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -16,17 +79,70 @@ impl PgnParseError {
     pub fn unexpected_eof(index: usize) -> Self {
         Self {
             index,
+            kind: ErrorKind::Syntax,
             message: format!("Unexpected end of file at index: {}", index),
+            location: None,
         }
     }
 
     pub fn syntax(index: usize, custom_message: &str) -> Self {
         Self {
             index,
+            kind: ErrorKind::Syntax,
             message: format!("Syntax error at index {}: {}", index, custom_message),
+            location: None,
         }
     }
 
+    /// `cmove`, read at `index`, doesn't fit the position it is played in.
+    pub fn move_error(index: usize, error: MoveError, cmove: &CMove) -> Self {
+        let (number, side) = match cmove.color {
+            Color::White => (format!("{}.", cmove.move_number), "White"),
+            Color::Black => (format!("{}...", cmove.move_number), "Black"),
+        };
+        let san = cmove.to_san();
+        let reason = match error {
+            MoveError::Impossible => "no piece can go there".to_string(),
+            MoveError::Illegal => "it is illegal".to_string(),
+            MoveError::Ambiguous => "it doesn't say which piece moves".to_string(),
+            MoveError::WrongSuffix { check, checkmate } => match (check, checkmate) {
+                (_, true) => "it is checkmate".to_string(),
+                (true, false) => "it gives check".to_string(),
+                (false, false) => "it gives no check".to_string(),
+            },
+            MoveError::WrongCapture { captures: true } => "it takes a piece".to_string(),
+            MoveError::WrongCapture { captures: false } => "there is nothing to take".to_string(),
+        };
+        Self {
+            index,
+            kind: ErrorKind::Move {
+                error,
+                move_number: cmove.move_number,
+                color: cmove.color,
+            },
+            message: format!("Move {number} {san} by {side} can't be played: {reason}."),
+            location: None,
+        }
+    }
+
+    /// Find where the error is in `input`, from the `spans` of the tokens it was parsed from.
+    pub fn locate(mut self, input: &str, spans: &[Span]) -> Self {
+        // Past the last token, the error is at the end of the text
+        let offset = spans
+            .get(self.index)
+            .map_or(input.trim_end().len(), |span| span.start);
+        let line_start = input[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |index| offset + index);
+        self.location = Some(Location {
+            line: input[..offset].matches('\n').count() + 1,
+            column: input[line_start..offset].chars().count() + 1,
+            snippet: input[line_start..line_end].trim_end().to_string(),
+        });
+        self
+    }
+
     pub fn expression_parsing_error(index: usize) -> Self {
         Self::syntax(
             index,
@@ -55,6 +171,20 @@ impl PgnParseError {
         )
     }
 
+    pub fn tag_parsing_error(index: usize) -> Self {
+        Self::syntax(
+            index,
+            "Failed to parse tag pair. Expected '[' followed by tag name, quoted value and ']'.",
+        )
+    }
+
+    pub fn fen_tag_error(index: usize) -> Self {
+        Self::syntax(
+            index,
+            "Invalid FEN tag. Expected a legal starting position.",
+        )
+    }
+
     pub fn checkmate_parsing_error(index: usize) -> Self {
         Self::syntax(index, "Failed to parse checkmate. Expected '#'.")
     }
@@ -88,6 +218,14 @@ impl PgnParseError {
         )
     }
 
+    pub fn long_move_parsing_error(index: usize) -> Self {
+        Self::syntax(index, "Failed to parse long algebraic move. Expected source and destination squares, possibly with capture and/or promotion.")
+    }
+
+    pub fn hyphen_parsing_error(index: usize) -> Self {
+        Self::syntax(index, "Failed to parse hyphen. Expected '-'.")
+    }
+
     pub fn pawn_move_parsing_error(index: usize) -> Self {
         Self::syntax(index, "Failed to parse pawn move. Expected file and rank, possibly with capture and/or promotion.")
     }
@@ -1,61 +1,246 @@
-use crate::common::piece::Piece;
+use crate::{
+    common::piece::Piece,
+    logic::movetree::treenode::{CastleSide, Nag},
+};
 
+/// Where a token is in the source, as byte offsets.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The tokens the parser reads from `input`, lexed as they are needed.
+pub type Tokens<'a> = std::iter::FilterMap<Lexer<'a>, fn((Token, Span)) -> Option<Token>>;
+
+/// Lex `input` as the parser reads it, escape lines being left out.
+pub fn tokens(input: &str) -> Tokens<'_> {
+    Lexer::new(input).filter_map(|(token, _)| (!matches!(token, Token::Escape(_))).then_some(token))
+}
+
+#[cfg(test)]
 pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
+    tokens(input).collect()
+}
+
+/// Split `input` in the tokens the parser reads, along with where each of them is in `input`.
+pub fn tokenize_with_spans(input: &str) -> (Vec<Token>, Vec<Span>) {
+    Lexer::new(input)
+        .filter(|(token, _)| !matches!(token, Token::Escape(_)))
+        .unzip()
+}
+
+/// Castling at the start of `text`, written with letters or zeros, and its length.
+fn castling(text: &str) -> Option<(CastleSide, usize)> {
+    if text.starts_with("O-O-O") || text.starts_with("0-0-0") {
+        Some((CastleSide::Long, 5))
+    } else if text.starts_with("O-O") || text.starts_with("0-0") {
+        Some((CastleSide::Short, 3))
+    } else {
+        None
+    }
+}
+
+/// Whether `text` starts with two squares written together, as in UCI's `e2e4`.
+fn joined_squares(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 4
+        && matches!(bytes[0], b'a'..=b'h')
+        && matches!(bytes[1], b'1'..=b'8')
+        && matches!(bytes[2], b'a'..=b'h')
+        && matches!(bytes[3], b'1'..=b'8')
+}
+
+/// Length of the start of `text` whose characters all match `predicate`.
+fn length_while(text: &str, predicate: impl Fn(char) -> bool) -> usize {
+    text.find(|char| !predicate(char)).unwrap_or(text.len())
+}
+
+/// Split a PGN in tokens as they are iterated, along with where each of them is.
+///
+/// Moves may also be written in long algebraic (`Ng1-f3`) or UCI (`e7e8q`) notation, with
+/// figurines (`♘f3`) and with zeros for castling. Squares written together are split by a
+/// [`Token::Hyphen`] that takes no room in the input.
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    /// Byte offset of the next token.
+    position: usize,
+    /// Whether the lexer is inside a tag pair, whose name is a symbol.
+    in_tag: bool,
+    /// Where a hyphen goes between two squares written together.
+    split_at: Option<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            position: 0,
+            in_tag: false,
+            split_at: None,
+        }
+    }
+
+    /// The token at the start of `rest`, which is in the first column of a line when
+    /// `line_start`, and its length.
+    fn token(&mut self, rest: &str, line_start: bool) -> (Token, usize) {
+        let char = rest.chars().next().unwrap();
+        let next_is = |next: char| rest[char.len_utf8()..].starts_with(next);
 
-    let mut chars = input.char_indices().peekable();
+        if self.in_tag {
+            return match char {
+                ']' => {
+                    self.in_tag = false;
+                    (Token::EndTag, 1)
+                }
+                '"' => string(rest),
+                _ if char.is_alphanumeric() || char == '_' => {
+                    let length = length_while(rest, |char| char.is_alphanumeric() || char == '_');
+                    (Token::Symbol(rest[..length].to_string()), length)
+                }
+                _ => (Token::Invalid, char.len_utf8()),
+            };
+        }
 
-    while let Some((idx, char)) = chars.next() {
         let token = match char {
-            ' ' | '\n' | '\t' => continue,
+            // Lines meant for other software
+            '%' if line_start => {
+                let length = length_while(rest, |char| char != '\n');
+                return (Token::Escape(rest[1..length].trim().to_string()), length);
+            }
+            ';' => {
+                let length = length_while(rest, |char| char != '\n');
+                return (
+                    Token::Comment(rest[1..length].trim_end().to_string()),
+                    length,
+                );
+            }
+            '{' => {
+                return match rest.find('}') {
+                    Some(end) => (Token::Comment(rest[1..end].to_string()), end + 1),
+                    None => (Token::Comment(rest[1..].to_string()), rest.len()),
+                };
+            }
+            '"' => return string(rest),
+            '[' => {
+                self.in_tag = true;
+                Token::StartTag
+            }
+            ']' => Token::EndTag,
             '/' => Token::Slash,
             '*' => Token::Star,
             '.' => Token::Dot,
             '(' => Token::StartVariation,
             ')' => Token::EndVariation,
-            '{' => {
-                let comment: String = chars
-                    .by_ref()
-                    .take_while(|(_, char)| *char != '}')
-                    .map(|(_, char)| char)
-                    .collect();
-                Token::Comment(comment)
+            'O' | '0' if castling(rest).is_some() => {
+                let (side, length) = castling(rest).unwrap();
+                return (Token::Castles(side), length);
+            }
+            '0'..='9' => {
+                let length = length_while(rest, |char| char.is_ascii_digit());
+                return match rest[..length].parse() {
+                    Ok(number) => (Token::Number(number), length),
+                    Err(_) => (Token::Invalid, length),
+                };
+            }
+            'a'..='h' => {
+                if joined_squares(rest) {
+                    self.split_at = Some(self.position + 2);
+                }
+                Token::File(char)
             }
-            '0'..='9' => Token::Number(char.to_digit(10).unwrap()),
-            'a'..='h' => Token::File(char),
             '=' => Token::Equals,
             '#' => Token::Checkmate,
             '+' => Token::Check,
             'x' => Token::Captures,
-            'N' => Token::Piece(Piece::Knight),
-            'B' => Token::Piece(Piece::Bishop),
-            'K' => Token::Piece(Piece::King),
-            'Q' => Token::Piece(Piece::Queen),
-            'R' => Token::Piece(Piece::Rook),
+            'N' | '♘' | '♞' => Token::Piece(Piece::Knight),
+            'B' | '♗' | '♝' => Token::Piece(Piece::Bishop),
+            'K' | '♔' | '♚' => Token::Piece(Piece::King),
+            'Q' | '♕' | '♛' => Token::Piece(Piece::Queen),
+            'R' | '♖' | '♜' => Token::Piece(Piece::Rook),
+            // UCI promotions, a bishop being read as the b file
+            'n' => Token::Piece(Piece::Knight),
+            'q' => Token::Piece(Piece::Queen),
+            'r' => Token::Piece(Piece::Rook),
             '-' => Token::Hyphen,
-            '?' => {
-                if chars.next_if_eq(&(idx + 1, '?')).is_some() {
-                    Token::Nag(Nag::Blunder)
-                } else if chars.next_if_eq(&(idx + 1, '!')).is_some() {
-                    Token::Nag(Nag::Dubious)
-                } else {
-                    Token::Nag(Nag::Poor)
-                }
-            }
-            '!' => {
-                if chars.next_if_eq(&(idx + 1, '!')).is_some() {
-                    Token::Nag(Nag::Excellent)
-                } else if chars.next_if_eq(&(idx + 1, '?')).is_some() {
-                    Token::Nag(Nag::Interesting)
-                } else {
-                    Token::Nag(Nag::Good)
-                }
+            '?' if next_is('?') => return (Token::Nag(Nag::BLUNDER), 2),
+            '?' if next_is('!') => return (Token::Nag(Nag::DUBIOUS), 2),
+            '?' => Token::Nag(Nag::POOR),
+            '!' if next_is('!') => return (Token::Nag(Nag::EXCELLENT), 2),
+            '!' if next_is('?') => return (Token::Nag(Nag::INTERESTING), 2),
+            '!' => Token::Nag(Nag::GOOD),
+            '$' => {
+                let length = 1 + length_while(&rest[1..], |char| char.is_ascii_digit());
+                return match rest[1..length].parse() {
+                    Ok(nag) => (Token::Nag(Nag(nag)), length),
+                    // Glyphs only go up to $255
+                    Err(_) => (Token::Invalid, length),
+                };
             }
             _ => Token::Invalid,
         };
-        tokens.push(token);
+        (token, char.len_utf8())
+    }
+}
+
+/// The quoted string at the start of `text` and its length, only quotes and backslashes are
+/// escaped inside it.
+fn string(text: &str) -> (Token, usize) {
+    let mut string = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((index, char)) = chars.next() {
+        match char {
+            '"' => return (Token::String(string), index + 1),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    string.push(escaped);
+                }
+            }
+            _ => string.push(char),
+        }
+    }
+    (Token::String(string), text.len())
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.split_at == Some(self.position) {
+            self.split_at = None;
+            let span = Span {
+                start: self.position,
+                end: self.position,
+            };
+            return Some((Token::Hyphen, span));
+        }
+
+        loop {
+            let rest = &self.input[self.position..];
+            let skipped = length_while(rest, char::is_whitespace);
+            self.position += skipped;
+            let rest = &self.input[self.position..];
+            if rest.is_empty() {
+                return None;
+            }
+
+            let line_start = self.position == 0 || self.input[..self.position].ends_with('\n');
+            let start = self.position;
+            let (token, length) = self.token(rest, line_start);
+            self.position += length;
+
+            // Pawn moves are written without their piece
+            if rest.starts_with(['♙', '♟']) {
+                continue;
+            }
+            let span = Span {
+                start,
+                end: self.position,
+            };
+            return Some((token, span));
+        }
     }
-    tokens
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -71,19 +256,172 @@ pub enum Token {
     Equals,
     Dot,
     Captures,
+    Castles(CastleSide),
     Check,
     Checkmate,
     StartVariation,
     EndVariation,
+    StartTag,
+    EndTag,
+    Symbol(String),
+    String(String),
+    /// A line starting with '%' in its first column, meant for other software.
+    Escape(String),
     Invalid,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub enum Nag {
-    Good,
-    Excellent,
-    Interesting,
-    Blunder,
-    Poor,
-    Dubious,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_pair() {
+        assert_eq!(
+            tokenize(r#"[White "Kasparov, \"Garry\""]"#),
+            vec![
+                Token::StartTag,
+                Token::Symbol("White".to_string()),
+                Token::String("Kasparov, \"Garry\"".to_string()),
+                Token::EndTag
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let input = "[Event \"Café\"]\n1. e4 {Good} *";
+        let (tokens, spans) = tokenize_with_spans(input);
+        assert_eq!(tokens.len(), spans.len());
+        let text: Vec<_> = spans
+            .iter()
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "[",
+                "Event",
+                "\"Café\"",
+                "]",
+                "1",
+                ".",
+                "e",
+                "4",
+                "{Good}",
+                "*"
+            ]
+        );
+    }
+
+    #[test]
+    fn other_notations() {
+        assert_eq!(
+            tokenize("O-O-O 0-0 0-1 ♘f3 ♙e4"),
+            vec![
+                Token::Castles(CastleSide::Long),
+                Token::Castles(CastleSide::Short),
+                Token::Number(0),
+                Token::Hyphen,
+                Token::Number(1),
+                Token::Piece(Piece::Knight),
+                Token::File('f'),
+                Token::Number(3),
+                Token::File('e'),
+                Token::Number(4),
+            ]
+        );
+
+        let input = "e7e8q";
+        let (tokens, spans) = tokenize_with_spans(input);
+        assert_eq!(
+            tokens,
+            [
+                Token::File('e'),
+                Token::Number(7),
+                Token::Hyphen,
+                Token::File('e'),
+                Token::Number(8),
+                Token::Piece(Piece::Queen)
+            ]
+        );
+        let text: Vec<_> = spans
+            .iter()
+            .map(|span| &input[span.start..span.end])
+            .collect();
+        assert_eq!(text, ["e", "7", "", "e", "8", "q"]);
+    }
+
+    #[test]
+    fn whole_numbers() {
+        assert_eq!(
+            tokenize("12. e4 1/2-1/2"),
+            vec![
+                Token::Number(12),
+                Token::Dot,
+                Token::File('e'),
+                Token::Number(4),
+                Token::Number(1),
+                Token::Slash,
+                Token::Number(2),
+                Token::Hyphen,
+                Token::Number(1),
+                Token::Slash,
+                Token::Number(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_and_rest_of_line_comments() {
+        let input = "% Exported by a tool\n1. e4 ; Best by test\n 1... e5 % *\n %";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            lexer.next(),
+            Some((
+                Token::Escape("Exported by a tool".to_string()),
+                Span { start: 0, end: 20 }
+            ))
+        );
+        // Lexing goes on from where it stopped
+        let rest: Vec<_> = lexer.clone().map(|(token, _)| token).collect();
+        assert_eq!(
+            rest,
+            [
+                Token::Number(1),
+                Token::Dot,
+                Token::File('e'),
+                Token::Number(4),
+                Token::Comment(" Best by test".to_string()),
+                Token::Number(1),
+                Token::Dot,
+                Token::Dot,
+                Token::Dot,
+                Token::File('e'),
+                Token::Number(5),
+                // Only at the start of a line
+                Token::Invalid,
+                Token::Star,
+                // In its first column
+                Token::Invalid,
+            ]
+        );
+        assert_eq!(lexer.count(), rest.len());
+
+        // The parser never sees escapes
+        assert_eq!(tokenize(input)[0], Token::Number(1));
+    }
+
+    #[test]
+    fn nags() {
+        assert_eq!(
+            tokenize("!! ?! $14 $255 $256"),
+            vec![
+                Token::Nag(Nag::EXCELLENT),
+                Token::Nag(Nag::DUBIOUS),
+                Token::Nag(Nag::WHITE_SLIGHT_ADVANTAGE),
+                Token::Nag(Nag(255)),
+                Token::Invalid
+            ]
+        );
+    }
 }
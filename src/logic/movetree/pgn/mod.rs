@@ -1,3 +1,4 @@
+pub mod database;
 pub mod errors;
 pub mod lexer;
 pub mod parser;
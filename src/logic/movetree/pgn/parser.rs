@@ -1,20 +1,31 @@
 use std::{
     fmt::{Debug, Display},
     iter::Peekable,
-    slice::Iter,
     str::FromStr,
 };
 
 use crate::{
-    common::{board::Board, color::Color, file::File, piece::Piece, rank::Rank, square::Square},
-    error::Error,
+    common::{
+        board::Board,
+        color::Color,
+        file::File,
+        piece::{Piece, PROMOTION_PIECES},
+        r#move::Move,
+        rank::Rank,
+        square::Square,
+    },
     logic::movetree::{
-        treenode::{CMove, CMoveKind, CResult, CastleSide, Fen, MoveDetails, TreeNode},
+        commands::Commands,
+        tags::Tags,
+        treenode::{CMove, CMoveKind, CResult, CastleSide, Fen, MoveDetails, Nag, TreeNode},
         MoveTree,
     },
 };
 
-use super::{errors::PgnParseError, lexer::Token};
+use super::{
+    errors::{MoveError, PgnParseError},
+    lexer::{tokens, Token, Tokens},
+};
 
 pub const STARTING_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
@@ -26,29 +37,41 @@ pub const STARTING_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ
 // PC: P'x'FR | PFxFR | PRxFR # Piece capture
 // PM1: FR | FxFR | FR=P | FxFR=P  # Pawn Move
 // C: { string }          # Comment
-// C1: '0-0' | '0-0-0'    # Castling
+// C1: 'O-O' | 'O-O-O'    # Castling (also written with zeros)
+// LM: P? F R ('-' | 'x') F R PR?  # Long algebraic move (and UCI)
+// PR: '='? P | 'q' | 'r' | 'b' | 'n'  # Promotion of a long algebraic move
 // MN: [0-9]+             # Move Number
 // D: .                   # Dot
 // CH: + | #              # Check/Checkmate
-// M: (PM | PM1 | C1) CH?  # Move (with optional check/checkmate)
+// NAG: ! | ? | !! | ?? | !? | ?! | '$' [0-9]+  # Annotation glyph
+// M: (LM | PM | PM1 | C1) CH? NAG*  # Move (with optional check/checkmate and annotations)
 // MT: M | MN D M | MN DDD M  # Move Text
 // V: ( E )               # Variation
-// E: MT C | V | E E    # Element (allows for comments and variations between moves)
+// E: C* MT C* | C+ | V | E E  # Element (allows for comments and variations between moves)
 // R: '1-0' | '0-1' | '1/2-1/2' | '*'  # Result
-// TS: '[' string string ']'  # Tag Section
+// TS: '[' symbol string ']'  # Tag Section
 // G: TS* E* R           # Game (with optional tags, multiple elements, and result)
 
 #[derive(Debug)]
-pub struct PgnParser<'a> {
+pub struct PgnParser<I: Iterator<Item = Token>> {
     move_tree: MoveTree,
-    tokens: Peekable<Iter<'a, Token>>,
+    tokens: Peekable<I>,
     cursor: usize,
+    /// Whether what can't be parsed is skipped rather than failing the whole game.
+    lenient: bool,
+    /// What was skipped in lenient mode.
+    warnings: Vec<PgnParseError>,
+    /// How many variations the parser is in.
+    depth: usize,
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
-    Move(CMove),
+    /// A move, with the index of its first token.
+    Move(CMove, usize),
     Variation(Vec<Expression>),
+    /// Comments that aren't followed by a move, e.g. after a variation.
+    Comments(Vec<String>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -68,12 +91,29 @@ impl Display for MoveNumber {
     }
 }
 
-impl<'a> PgnParser<'a> {
-    pub fn new(tokens: Iter<'a, Token>) -> Self {
+impl<'a> PgnParser<std::iter::Cloned<std::slice::Iter<'a, Token>>> {
+    /// Parse tokens that were already lexed.
+    pub fn new(tokens: std::slice::Iter<'a, Token>) -> Self {
+        Self::from_tokens(tokens.cloned())
+    }
+}
+
+impl<'a> PgnParser<Tokens<'a>> {
+    /// Parse `input` as it is lexed, without keeping all of its tokens.
+    pub fn from_text(input: &'a str) -> Self {
+        Self::from_tokens(tokens(input))
+    }
+}
+
+impl<I: Iterator<Item = Token> + Clone> PgnParser<I> {
+    fn from_tokens(tokens: I) -> Self {
         Self {
             move_tree: MoveTree::default(),
             tokens: tokens.peekable(),
             cursor: 0,
+            lenient: false,
+            warnings: vec![],
+            depth: 0,
         }
     }
 
@@ -81,9 +121,20 @@ impl<'a> PgnParser<'a> {
         // let mut move_tree = MoveTree::new();
         let mut current = self.move_tree.game_start();
 
+        self.parse_tags();
+
+        if let (Some("1"), Some(fen)) = (
+            self.move_tree.tags.get("SetUp"),
+            self.move_tree.tags.get("FEN"),
+        ) {
+            let fen = fen.to_string();
+            self.move_tree
+                .set_root_fen(&fen)
+                .map_err(|_| PgnParseError::fen_tag_error(self.cursor))?;
+        }
+
         while let Ok(expression) = self.expression() {
-            let node = self.add_expression_to_tree(expression, current);
-            current = node;
+            current = self.add_expression_to_tree(expression, current)?;
         }
 
         let result = self.result()?;
@@ -93,66 +144,235 @@ impl<'a> PgnParser<'a> {
         Ok(self.move_tree.clone())
     }
 
-    fn add_move_to_tree(&mut self, cmove: CMove, parent: indextree::NodeId) -> indextree::NodeId {
-        // Not convinced this works but tests pass...
-        if let Ok(fen) = generate_next_fen(&self.get_last_fen(parent), &cmove) {
-            let new_node = self
-                .move_tree
-                .tree
-                .new_node(TreeNode::Move(fen.clone(), cmove));
-            parent.append(new_node, &mut self.move_tree.tree);
-            new_node
-        } else {
-            match self.move_tree.tree[parent].parent() {
-                Some(grandparent) => {
-                    let fen = self.get_last_fen(grandparent);
-                    let new_node = self
-                        .move_tree
-                        .tree
-                        .new_node(TreeNode::Move(fen.clone(), cmove));
-                    parent.append(new_node, &mut self.move_tree.tree);
-                    new_node
+    /// Parse as much of the game as possible, skipping what can't be read.
+    ///
+    /// Anything that isn't a move, comment or variation is skipped up to the next move number,
+    /// result or end of variation. A line stops at a move that can't be played, and a wrong
+    /// check or mate suffix is corrected. Returns the moves that could be read, with an error
+    /// for each part that was skipped or corrected.
+    pub fn parse_lenient(&mut self) -> (MoveTree, Vec<PgnParseError>) {
+        self.lenient = true;
+        let mut current = self.move_tree.game_start();
+
+        self.parse_tags();
+
+        if let (Some("1"), Some(fen)) = (
+            self.move_tree.tags.get("SetUp"),
+            self.move_tree.tags.get("FEN"),
+        ) {
+            let fen = fen.to_string();
+            // The moves can't be played from an unknown position
+            if self.move_tree.set_root_fen(&fen).is_err() {
+                self.warnings
+                    .push(PgnParseError::fen_tag_error(self.cursor));
+                return (self.move_tree.clone(), std::mem::take(&mut self.warnings));
+            }
+        }
+
+        // The moves after one that can't be played can't be played either
+        let mut unplayable = false;
+        loop {
+            if let Ok(expression) = self.expression() {
+                if unplayable {
+                    continue;
+                }
+                match self.add_expression_to_tree(expression, current) {
+                    Ok(node) => current = node,
+                    Err(error) => {
+                        self.warnings.push(error);
+                        unplayable = true;
+                    }
                 }
-                None => panic!("Could not add move: {} to tree", cmove.to_san()),
+            } else if let Ok(result) = self.result() {
+                let new_node = self.move_tree.tree.new_node(TreeNode::Result(result));
+                current.append(new_node, &mut self.move_tree.tree);
+                break;
+            } else if self.tokens.peek().is_none() {
+                self.warnings
+                    .push(PgnParseError::unexpected_eof(self.cursor));
+                break;
+            } else {
+                self.skip();
+            }
+        }
+
+        (self.move_tree.clone(), std::mem::take(&mut self.warnings))
+    }
+
+    /// Skip what can't be parsed, up to where the movetext can be read again.
+    fn skip(&mut self) {
+        if let Some(Token::EndVariation) = self.tokens.peek() {
+            self.warnings.push(PgnParseError::syntax(
+                self.cursor,
+                "Unexpected ')' outside of a variation.",
+            ));
+            self.consume();
+            return;
+        }
+
+        self.warnings
+            .push(PgnParseError::expression_parsing_error(self.cursor));
+        self.consume();
+        while self.tokens.peek().is_some() && !self.at_resync_point() {
+            self.consume();
+        }
+    }
+
+    /// Whether the next tokens are a move number, a result or the end of the variation.
+    fn at_resync_point(&mut self) -> bool {
+        match self.tokens.peek() {
+            Some(Token::Star) => true,
+            Some(Token::EndVariation) => self.depth > 0,
+            Some(Token::Number(_)) => {
+                let mut next = self.tokens.clone();
+                next.next();
+                matches!(next.next(), Some(Token::Dot | Token::Hyphen | Token::Slash))
+            }
+            _ => false,
+        }
+    }
+
+    /// Read the tag pair section, leaving the parser at the start of the movetext.
+    pub fn parse_tags(&mut self) -> Tags {
+        while let Ok((name, value)) = self.tag_pair() {
+            self.move_tree.tags.set(&name, &value);
+        }
+        self.move_tree.tags.clone()
+    }
+
+    fn add_move_to_tree(
+        &mut self,
+        mut cmove: CMove,
+        index: usize,
+        parent: indextree::NodeId,
+    ) -> Result<indextree::NodeId, PgnParseError> {
+        let last_fen = self.get_last_fen(parent);
+        // Comments before the first move describe the whole game or variation
+        if !matches!(self.move_tree.tree[parent].get(), TreeNode::Move(..)) {
+            let comments = std::mem::take(&mut cmove.comments_before);
+            self.add_comments_to_tree(comments, parent);
+        }
+        // Move text may leave out the move number, the position never does
+        if let Ok(board) = Board::from_str(&last_fen) {
+            cmove.color = board.side_to_move();
+            cmove.move_number = board.fullmoves() as usize;
+            if let Some(kind) = san_kind(&board, &cmove.kind) {
+                cmove.kind = kind;
+            }
+        }
+
+        let fen = generate_next_fen(&last_fen, &cmove)
+            .map_err(|error| PgnParseError::move_error(index, error, &cmove))?;
+
+        let board = Board::from_str(&fen).expect("To be able to create a valid fen");
+        let previous = Board::from_str(&last_fen).expect("To be able to create a valid fen");
+        // A capture leaves one piece less on the board, en passant included
+        let captures = board.occupied().count() < previous.occupied().count();
+        let marked = match &cmove.kind {
+            CMoveKind::Regular(details) => details.captures,
+            CMoveKind::Castles(_) => false,
+        };
+        if marked != captures {
+            let error =
+                PgnParseError::move_error(index, MoveError::WrongCapture { captures }, &cmove);
+            if !self.lenient {
+                return Err(error);
+            }
+            self.warnings.push(error);
+            if let CMoveKind::Regular(details) = &mut cmove.kind {
+                details.captures = captures;
+            }
+        }
+
+        let (check, checkmate) = (board.is_check(), board.is_checkmate());
+        if (cmove.check || cmove.checkmate) != check || cmove.checkmate != checkmate {
+            let error = PgnParseError::move_error(
+                index,
+                MoveError::WrongSuffix { check, checkmate },
+                &cmove,
+            );
+            if !self.lenient {
+                return Err(error);
             }
+            self.warnings.push(error);
+            cmove.check = check && !checkmate;
+            cmove.checkmate = checkmate;
         }
+
+        let new_node = self.move_tree.tree.new_node(TreeNode::Move(fen, cmove));
+        parent.append(new_node, &mut self.move_tree.tree);
+        Ok(new_node)
     }
 
+    /// Add `expression` after `parent`, returning the node the next move follows.
     fn add_expression_to_tree(
         &mut self,
         expression: Expression,
         parent: indextree::NodeId,
-    ) -> indextree::NodeId {
+    ) -> Result<indextree::NodeId, PgnParseError> {
         match expression {
-            Expression::Move(cmove) => self.add_move_to_tree(cmove, parent),
-            Expression::Variation(expressions) => self.add_variation_to_tree(expressions, parent),
+            Expression::Move(cmove, index) => self.add_move_to_tree(cmove, index, parent),
+            Expression::Variation(expressions) => {
+                self.add_variation_to_tree(expressions, parent)?;
+                Ok(parent)
+            }
+            Expression::Comments(comments) => {
+                self.add_comments_to_tree(comments, parent);
+                Ok(parent)
+            }
+        }
+    }
+
+    fn add_comments_to_tree(&mut self, mut new_comments: Vec<String>, node: indextree::NodeId) {
+        match self.move_tree.tree[node].get_mut() {
+            TreeNode::Move(_, cmove) => {
+                cmove.commands.take_from(&mut new_comments);
+                cmove.comments_after.append(&mut new_comments)
+            }
+            TreeNode::GameStart(comments, commands) => {
+                commands.take_from(&mut new_comments);
+                comments.append(&mut new_comments)
+            }
+            TreeNode::StartVariation(comments) => comments.append(&mut new_comments),
+            TreeNode::EndVariation | TreeNode::Result(_) => (),
         }
     }
 
+    /// Add a variation replacing the move at `parent`.
+    ///
+    /// In lenient mode, the variation stops before a move that can't be played.
     fn add_variation_to_tree(
         &mut self,
         expressions: Vec<Expression>,
         parent: indextree::NodeId,
-    ) -> indextree::NodeId {
-        let grand_parent = self.move_tree.tree[parent].parent().unwrap();
-        let start_variation = self.move_tree.tree.new_node(TreeNode::StartVariation);
+    ) -> Result<(), PgnParseError> {
+        let grand_parent = self.move_tree.tree[parent].parent().unwrap_or(parent);
+        let start_variation = self
+            .move_tree
+            .tree
+            .new_node(TreeNode::StartVariation(vec![]));
         grand_parent.append(start_variation, &mut self.move_tree.tree);
 
         let mut var_current = start_variation;
-
         for expression in expressions {
-            let new_node = self.add_expression_to_tree(expression, var_current);
-            var_current = if let TreeNode::EndVariation = self.move_tree.tree[new_node].get() {
-                parent
-            } else {
-                new_node
+            match self.add_expression_to_tree(expression, var_current) {
+                Ok(node) => var_current = node,
+                Err(error) if self.lenient => {
+                    self.warnings.push(error);
+                    break;
+                }
+                Err(error) => return Err(error),
             }
         }
 
-        let end_variation = self.move_tree.tree.new_node(TreeNode::EndVariation);
-        var_current.append(end_variation, &mut self.move_tree.tree);
-
-        end_variation
+        // Nothing is left of a variation whose first move can't be played
+        if self.move_tree.tree[start_variation].first_child().is_none() {
+            start_variation.remove_subtree(&mut self.move_tree.tree);
+        } else {
+            let end_variation = self.move_tree.tree.new_node(TreeNode::EndVariation);
+            var_current.append(end_variation, &mut self.move_tree.tree);
+        }
+        Ok(())
     }
 
     // If given node has FEN, return it
@@ -169,23 +389,25 @@ impl<'a> PgnParser<'a> {
                 current = parent;
             }
         }
-        // If we can't find a parent move, return the starting position
-        STARTING_POSITION_FEN.to_string()
+        // If we can't find a parent move, return the position the game starts from
+        self.move_tree.root_fen.clone()
     }
 
-    // E: MT C? | V | E E    # Element (allows for comments and variations between moves)
+    // E: C* MT C* | C+ | V | E E  # Element (allows for comments and variations between moves)
     fn expression(&mut self) -> Result<Expression, PgnParseError> {
+        let comments_before = self.comments();
+        let index = self.cursor;
         if let Ok(mut move_text) = self.move_text() {
-            if let Ok(comment) = self.comment() {
-                move_text.comment = Some(comment);
-                Ok(Expression::Move(move_text))
-            } else {
-                Ok(Expression::Move(move_text))
-            }
+            move_text.comments_before = comments_before;
+            move_text.comments_after = self.comments();
+            move_text.commands.take_from(&mut move_text.comments_after);
+            Ok(Expression::Move(move_text, index))
+        } else if !comments_before.is_empty() {
+            Ok(Expression::Comments(comments_before))
         } else if let Ok(variation) = self.variation() {
             Ok(Expression::Variation(variation))
         } else {
-            return Err(PgnParseError::expression_parsing_error(self.cursor));
+            Err(PgnParseError::expression_parsing_error(self.cursor))
         }
     }
 
@@ -196,21 +418,39 @@ impl<'a> PgnParser<'a> {
 
         if let Some(Token::StartVariation) = self.tokens.peek() {
             self.consume();
-            let mut expressions = vec![];
-            while let Some(token) = self.tokens.peek() {
-                if token == &&Token::EndVariation {
-                    self.consume();
-                    return Ok(expressions);
-                }
+            self.depth += 1;
+            let variation = self.variation_expressions();
+            self.depth -= 1;
+            return variation;
+        }
 
-                if let Ok(expression) = self.expression() {
-                    expressions.push(expression)
-                } else {
-                    return Err(PgnParseError::variation_parsing_error(self.cursor));
-                }
+        Err(PgnParseError::variation_parsing_error(self.cursor))
+    }
+
+    /// The inside of a variation, up to and including its ')'.
+    fn variation_expressions(&mut self) -> Result<Vec<Expression>, PgnParseError> {
+        let mut expressions = vec![];
+        while let Some(token) = self.tokens.peek() {
+            if token == &Token::EndVariation {
+                self.consume();
+                return Ok(expressions);
+            }
+
+            if let Ok(expression) = self.expression() {
+                expressions.push(expression)
+            } else if self.lenient {
+                self.skip();
+            } else {
+                return Err(PgnParseError::variation_parsing_error(self.cursor));
             }
         }
 
+        // A variation left open keeps the moves read so far
+        if self.lenient {
+            self.warnings
+                .push(PgnParseError::unexpected_eof(self.cursor));
+            return Ok(expressions);
+        }
         Err(PgnParseError::variation_parsing_error(self.cursor))
     }
 
@@ -295,17 +535,62 @@ impl<'a> PgnParser<'a> {
             None => Err(PgnParseError::unexpected_eof(self.cursor)),
         }
     }
+    // TS: '[' symbol string ']'  # Tag Section
+    fn tag_pair(&mut self) -> Result<(String, String), PgnParseError> {
+        let iter_save = self.tokens.clone();
+        let cursor_save = self.cursor;
+
+        if let Some(Token::StartTag) = self.tokens.peek() {
+            self.consume();
+            if let Some(Token::Symbol(name)) = self.tokens.peek().cloned() {
+                self.consume();
+                if let Some(Token::String(value)) = self.tokens.peek().cloned() {
+                    self.consume();
+                    if let Some(Token::EndTag) = self.tokens.peek() {
+                        self.consume();
+                        return Ok((name.to_string(), value.to_string()));
+                    }
+                }
+            }
+        }
+
+        self.tokens = iter_save;
+        self.cursor = cursor_save;
+        match self.tokens.peek() {
+            Some(_) => Err(PgnParseError::tag_parsing_error(self.cursor)),
+            None => Err(PgnParseError::unexpected_eof(self.cursor)),
+        }
+    }
+
     fn comment(&mut self) -> Result<String, PgnParseError> {
         match self.tokens.peek() {
             Some(Token::Comment(comment)) => {
+                let comment = comment.trim().to_string();
                 self.consume();
-                Ok(comment.to_string())
+                Ok(comment)
             }
             Some(_) => Err(PgnParseError::comment_parsing_error(self.cursor)),
             None => Err(PgnParseError::unexpected_eof(self.cursor)),
         }
     }
 
+    fn comments(&mut self) -> Vec<String> {
+        let mut comments = vec![];
+        while let Ok(comment) = self.comment() {
+            comments.push(comment);
+        }
+        comments
+    }
+
+    fn nags(&mut self) -> Vec<Nag> {
+        let mut nags = vec![];
+        while let Some(Token::Nag(nag)) = self.tokens.peek() {
+            nags.push(*nag);
+            self.consume();
+        }
+        nags
+    }
+
     fn checkmate(&mut self) -> Result<String, PgnParseError> {
         match self.tokens.peek() {
             Some(Token::Checkmate) => {
@@ -368,38 +653,33 @@ impl<'a> PgnParser<'a> {
         let cursor_save = self.cursor;
         match self.tokens.peek() {
             Some(Token::Number(number)) => {
-                let mut number = number.to_string();
+                let number = *number as usize;
                 self.consume();
-                loop {
-                    if self.dot().is_ok() {
-                        if self.dot().is_ok() {
-                            if self.dot().is_ok() {
-                                return Ok(MoveNumber::BlackMoveNumber(number.parse().unwrap()));
-                            } else {
-                                self.tokens = iter_save;
-                                self.cursor = cursor_save;
-                                return Err(PgnParseError::move_number_parsing_error(self.cursor));
-                            }
-                        } else {
-                            return Ok(MoveNumber::WhiteMoveNumber(number.parse().unwrap()));
-                        }
-                    } else if let Some(Token::Number(n)) = self.tokens.peek() {
-                        number.push_str(&n.to_string());
-                        self.consume();
-                    } else {
-                        self.consume();
-                        return Ok(MoveNumber::BlackMoveNumber(number.parse().unwrap()));
-                    }
+                // Black's moves may be numbered without dots
+                if self.dot().is_err() {
+                    return Ok(MoveNumber::BlackMoveNumber(number));
+                }
+                if self.dot().is_err() {
+                    return Ok(MoveNumber::WhiteMoveNumber(number));
+                }
+                if self.dot().is_ok() {
+                    return Ok(MoveNumber::BlackMoveNumber(number));
                 }
+                self.tokens = iter_save;
+                self.cursor = cursor_save;
+                Err(PgnParseError::move_number_parsing_error(self.cursor))
             }
             Some(_) => Err(PgnParseError::move_number_parsing_error(self.cursor)),
             None => Err(PgnParseError::unexpected_eof(self.cursor)),
         }
     }
 
-    // M: (PM | PM1 | C1) CH?  # Move (with optional check/checkmate)
+    // M: (LM | PM | PM1 | C1) CH? NAG*  # Move (with optional check/checkmate and annotations)
     fn r#move(&mut self) -> Result<CMove, PgnParseError> {
-        let move_kind = if let Ok(piece_move) = self.piece_move() {
+        // Tried first, its source square would otherwise be read as a move of its own
+        let move_kind = if let Ok(long_move) = self.long_move() {
+            long_move
+        } else if let Ok(piece_move) = self.piece_move() {
             piece_move
         } else if let Ok(pawn_move) = self.pawn_move() {
             pawn_move
@@ -409,33 +689,19 @@ impl<'a> PgnParser<'a> {
             return Err(PgnParseError::move_parsing_error(self.cursor));
         };
 
-        if self.check().is_ok() {
-            return Ok(CMove {
-                move_number: 0,
-                kind: move_kind,
-                check: true,
-                checkmate: false,
-                color: Color::White,
-                comment: None,
-            });
-        } else if self.checkmate().is_ok() {
-            return Ok(CMove {
-                move_number: 0,
-                kind: move_kind,
-                check: false,
-                checkmate: true,
-                color: Color::White,
-                comment: None,
-            });
-        }
+        let check = self.check().is_ok();
+        let checkmate = !check && self.checkmate().is_ok();
 
         Ok(CMove {
             move_number: 0,
             kind: move_kind,
-            check: false,
-            checkmate: false,
+            check,
+            checkmate,
             color: Color::White,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: self.nags(),
         })
     }
 
@@ -522,30 +788,98 @@ impl<'a> PgnParser<'a> {
 
     fn castle(&mut self) -> Result<CMoveKind, PgnParseError> {
         match self.tokens.peek() {
-            Some(Token::Number(0)) => {
-                let iter_save = self.tokens.clone();
-                let cursor_save = self.cursor;
+            Some(Token::Castles(side)) => {
+                let side = side.clone();
+                self.consume();
+                Ok(CMoveKind::Castles(side))
+            }
+            Some(_) => Err(PgnParseError::castle_parsing_error(self.cursor)),
+            None => Err(PgnParseError::unexpected_eof(self.cursor)),
+        }
+    }
 
+    // LM: P? F R ('-' | 'x') F R PR?  # Long algebraic move, UCI moves are read as 'e2-e4'
+    fn long_move(&mut self) -> Result<CMoveKind, PgnParseError> {
+        let iter_save = self.tokens.clone();
+        let cursor_save = self.cursor;
+
+        if let Some(details) = self.long_move_details() {
+            return Ok(CMoveKind::Regular(details));
+        }
+
+        self.tokens = iter_save;
+        self.cursor = cursor_save;
+        Err(PgnParseError::long_move_parsing_error(self.cursor))
+    }
+
+    fn long_move_details(&mut self) -> Option<MoveDetails> {
+        // The piece is left out of pawn moves and UCI moves, the position tells which it is
+        let piece = self.piece().unwrap_or(Piece::Pawn);
+        let src_file = self.file().ok()?;
+        let src_rank = self.rank().ok()?;
+        let captures = self.captures().is_ok();
+        if !captures {
+            self.hyphen().ok()?;
+        }
+        let dst_file = self.file().ok()?;
+        let dst_rank = self.rank().ok()?;
+        // Only what could be a pawn reaching the last rank promotes
+        let promotion = if piece == Piece::Pawn && matches!(dst_rank, Rank::First | Rank::Eighth) {
+            self.promotion()
+        } else {
+            None
+        };
+
+        Some(MoveDetails {
+            piece,
+            dst_rank,
+            dst_file,
+            captures,
+            src_rank: Some(src_rank),
+            src_file: Some(src_file),
+            promotion,
+        })
+    }
+
+    // PR: '='? P | 'q' | 'r' | 'b' | 'n'  # Promotion of a long algebraic move
+    fn promotion(&mut self) -> Option<Piece> {
+        let iter_save = self.tokens.clone();
+        let cursor_save = self.cursor;
+
+        let with_equals = self.equals().is_ok();
+        let piece = match self.tokens.peek() {
+            Some(Token::Piece(piece)) => Some(*piece),
+            // Unless it is the file of the next move
+            Some(Token::File('b')) => {
+                let mut next = self.tokens.clone();
+                next.next();
+                (!matches!(next.peek(), Some(Token::Number(_)))).then_some(Piece::Bishop)
+            }
+            _ => None,
+        };
+
+        match piece {
+            Some(piece) if PROMOTION_PIECES.contains(&piece) => {
                 self.consume();
-                if let Some(Token::Hyphen) = self.tokens.peek() {
-                    self.consume();
-                    if let Some(Token::Number(0)) = self.tokens.peek() {
-                        self.consume();
-                        if let Some(Token::Hyphen) = self.tokens.peek() {
-                            self.consume();
-                            if let Some(Token::Number(0)) = self.tokens.peek() {
-                                return Ok(CMoveKind::Castles(CastleSide::Long));
-                            }
-                        }
-                        return Ok(CMoveKind::Castles(CastleSide::Short));
-                    }
+                Some(piece)
+            }
+            _ => {
+                if with_equals {
+                    self.tokens = iter_save;
+                    self.cursor = cursor_save;
                 }
+                None
+            }
+        }
+    }
 
-                self.tokens = iter_save;
-                self.cursor = cursor_save;
-                Err(PgnParseError::castle_parsing_error(self.cursor))
+    fn hyphen(&mut self) -> Result<(), PgnParseError> {
+        match self.tokens.peek() {
+            Some(Token::Hyphen) => {
+                self.consume();
+                Ok(())
             }
-            Some(_) => Err(PgnParseError::castle_parsing_error(self.cursor)),
+            Some(_) => Err(PgnParseError::hyphen_parsing_error(self.cursor)),
             None => Err(PgnParseError::unexpected_eof(self.cursor)),
         }
     }
@@ -702,7 +1036,7 @@ impl<'a> PgnParser<'a> {
     // P: N, B, R, Q, K       # Piece
     fn piece(&mut self) -> Result<Piece, PgnParseError> {
         let piece = match self.tokens.peek() {
-            Some(Token::Piece(piece)) => {
+            Some(&Token::Piece(piece)) => {
                 self.consume();
                 piece
             }
@@ -710,7 +1044,7 @@ impl<'a> PgnParser<'a> {
             _ => return Err(PgnParseError::piece_parsing_error(self.cursor)),
         };
 
-        Ok(*piece)
+        Ok(piece)
     }
 
     fn captures(&mut self) -> Result<(), PgnParseError> {
@@ -742,12 +1076,12 @@ impl<'a> PgnParser<'a> {
     // R: 1 … 8               # Rank
     fn rank(&mut self) -> Result<Rank, PgnParseError> {
         match self.tokens.peek() {
-            Some(Token::Number(number)) => {
+            Some(&Token::Number(number)) => {
                 // NOTE: Feel this is a bit dangerous incase we encounter a move number from 1-8
                 // and parse it as rank but other grammar rule should fail meaning we dont eat this
                 // token as a rank when it isn't
                 self.consume();
-                let rank = Rank::try_from(number)
+                let rank = Rank::try_from(&number)
                     .map_err(|_| PgnParseError::rank_parsing_error(self.cursor))?;
                 Ok(rank)
             }
@@ -757,64 +1091,98 @@ impl<'a> PgnParser<'a> {
     }
 }
 
-pub fn generate_next_fen(current_fen: &str, cmove: &CMove) -> crate::Result<Fen> {
-    // NOTE: Currently board struct only handles promotion to queen
+/// The move as SAN writes it, when it is given with its source square as in long algebraic
+/// or UCI notation.
+///
+/// A piece other than the one on the source square is left for the move to be rejected.
+fn san_kind(board: &Board, kind: &CMoveKind) -> Option<CMoveKind> {
+    let CMoveKind::Regular(details) = kind else {
+        return None;
+    };
+    let (Some(src_file), Some(src_rank)) = (details.src_file, details.src_rank) else {
+        return None;
+    };
+    let from = Square::make_square(src_file, src_rank);
+    let piece = board.piece_on(from)?;
+    if piece != details.piece && details.piece != Piece::Pawn {
+        return None;
+    }
+    let next_move = Move {
+        from,
+        to: Square::make_square(details.dst_file, details.dst_rank),
+        promotion: details.promotion,
+    };
+    next_move
+        .try_into_cmove(*board)
+        .ok()
+        .map(|cmove| cmove.kind)
+}
+
+/// The position after `cmove`, checking that it can be played from `current_fen`.
+pub fn generate_next_fen(current_fen: &str, cmove: &CMove) -> Result<Fen, MoveError> {
     let mut board = Board::from_str(current_fen).expect("To be able to create a valid fen");
+    let side = board.side_to_move();
 
-    match &cmove.kind {
-        CMoveKind::Castles(side) => {
-            let (from, to) = match (cmove.color, side) {
+    let next_move = match &cmove.kind {
+        CMoveKind::Castles(castle_side) => {
+            let (from, to) = match (side, castle_side) {
                 (Color::White, CastleSide::Short) => (Square::E1, Square::G1),
                 (Color::White, CastleSide::Long) => (Square::E1, Square::C1),
                 (Color::Black, CastleSide::Short) => (Square::E8, Square::G8),
                 (Color::Black, CastleSide::Long) => (Square::E8, Square::C8),
             };
-            Ok(board
-                .update(crate::common::r#move::Move { from, to })
-                .to_string())
+            if !board.on_is(from, (Piece::King, side)) {
+                return Err(MoveError::Impossible);
+            }
+            Move::new(from, to)
         }
         CMoveKind::Regular(details) => {
             let dest = Square::make_square(details.dst_file, details.dst_rank);
-            let potential_source_squares = board.get_valid_moves_to(dest, details.piece);
-
-            if potential_source_squares.len() == 1 {
-                board.update(crate::common::r#move::Move {
-                    from: potential_source_squares.into_iter().next().unwrap(),
+            let disambiguates = |from: &Square| {
+                details.src_file.is_none_or(|file| from.file() == file)
+                    && details.src_rank.is_none_or(|rank| from.rank() == rank)
+            };
+            let sources: Vec<_> = board
+                .get_valid_moves_to(dest, details.piece)
+                .into_iter()
+                .filter(disambiguates)
+                .collect();
+
+            match sources[..] {
+                [from] => Move {
+                    from,
                     to: dest,
-                });
-
-                Ok(board.to_string())
-            } else {
-                // Handle disambiguation
-                let mut from_square = None;
-                for square in potential_source_squares {
-                    if (details.src_file.is_some() && square.file() == details.src_file.unwrap())
-                        || (details.src_rank.is_some()
-                            && square.rank() == details.src_rank.unwrap())
-                    {
-                        from_square = Some(square);
-                        break;
-                    }
+                    promotion: details.promotion,
+                },
+                // A piece that could get there if it weren't for its king is pinned
+                [] if board.pieces_of(details.piece, side).any(|from| {
+                    disambiguates(&from) && board.get_valid_moves(from).contains(&dest)
+                }) =>
+                {
+                    return Err(MoveError::Illegal)
                 }
-
-                if let Some(from) = from_square {
-                    board.update(crate::common::r#move::Move { from, to: dest });
-                } else {
-                    return Err(Error::FenGeneration {
-                        fen: board.to_string(),
-                        cmove: cmove.clone(),
-                    });
-                }
-                Ok(board.to_string())
+                [] => return Err(MoveError::Impossible),
+                _ => return Err(MoveError::Ambiguous),
             }
         }
+    };
+
+    // Pawns reaching the last rank promote, and only them
+    if !board.is_legal(next_move) || board.is_promotion(next_move) != next_move.promotion.is_some()
+    {
+        return Err(MoveError::Illegal);
     }
+    Ok(board.update(next_move).to_string())
 }
+
 #[cfg(test)]
 mod test {
+    use crate::logic::engine::uci::Score;
     use crate::logic::movetree::pgn::lexer::{tokenize, Token};
+    use std::time::Duration;
 
     use super::{PgnParseError, PgnParser, *};
+    use crate::logic::movetree::pgn::errors::ErrorKind;
 
     #[test]
     fn next_fen() {
@@ -834,7 +1202,10 @@ mod test {
                 check: false,
                 color: Color::White,
                 checkmate: false,
-                comment: None,
+                comments_before: vec![],
+                comments_after: vec![],
+                commands: Commands::default(),
+                nags: vec![],
             },
         );
         assert_eq!(
@@ -842,6 +1213,16 @@ mod test {
             Ok("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1".to_string())
         );
     }
+    #[test]
+    fn next_fen_underpromotion() {
+        let tokens = tokenize("exd8=N");
+        let cmove = PgnParser::new(tokens.iter()).r#move().unwrap();
+        assert_eq!(
+            generate_next_fen("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", &cmove),
+            Ok("3N3k/8/8/8/8/8/8/4K3 b - - 0 1".to_string())
+        );
+    }
+
     #[test]
     fn test_white_move_number() {
         let tokens = [Token::Number(1), Token::Dot];
@@ -858,20 +1239,14 @@ mod test {
 
     #[test]
     fn test_black_move_number_without_dots() {
-        let tokens = [Token::Number(1), Token::Number(1), Token::File('e')];
+        let tokens = [Token::Number(11), Token::File('e')];
         let mut parser = PgnParser::new(tokens.iter());
         assert_eq!(parser.move_number(), Ok(MoveNumber::BlackMoveNumber(11)));
     }
 
     #[test]
     fn test_multi_digit_move_number() {
-        let tokens = [
-            Token::Number(1),
-            Token::Number(0),
-            Token::Dot,
-            Token::Dot,
-            Token::Dot,
-        ];
+        let tokens = [Token::Number(10), Token::Dot, Token::Dot, Token::Dot];
         let mut parser = PgnParser::new(tokens.iter());
         assert_eq!(parser.move_number(), Ok(MoveNumber::BlackMoveNumber(10)));
     }
@@ -899,7 +1274,10 @@ mod test {
                 check: false,
                 color: Color::White,
                 checkmate: false,
-                comment: None,
+                comments_before: vec![],
+                comments_after: vec![],
+                commands: Commands::default(),
+                nags: vec![],
             }
         );
     }
@@ -1012,6 +1390,358 @@ mod test {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn parses_tag_pairs() {
+        let tokens = tokenize(
+            r#"[Event "Casual game"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+[Opening "King's Gambit"]
+
+1.e4 1-0"#,
+        );
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+        let tags = res.tags();
+        assert_eq!(tags.event.as_deref(), Some("Casual game"));
+        assert_eq!(tags.white.as_deref(), Some("Anderssen, Adolf"));
+        assert_eq!(tags.black.as_deref(), Some("Kieseritzky, Lionel"));
+        assert_eq!(tags.result.as_deref(), Some("1-0"));
+        assert_eq!(tags.get("Opening"), Some("King's Gambit"));
+        assert!(res
+            .generate_pgn(res.game_start())
+            .starts_with("[Event \"Casual game\"]\n[White \"Anderssen, Adolf\"]\n[Black \"Kieseritzky, Lionel\"]\n[Result \"1-0\"]\n[Opening \"King's Gambit\"]\n\n"));
+    }
+
+    #[test]
+    fn rejects_unterminated_tag_pair() {
+        let tokens = tokenize(r#"[Event "Casual game" 1.e4 1-0"#);
+        let mut parser = PgnParser::new(tokens.iter());
+        assert!(matches!(
+            parser.tag_pair(),
+            Err(PgnParseError { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn parses_game_from_fen_tag() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40";
+        let tokens = tokenize(&format!(
+            "[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n40... Kd7 41. e4 *"
+        ));
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+        assert_eq!(res.root_fen(), fen);
+
+        let (first, fen_after_first, _) = res.get_next_move(res.game_start())[0].clone();
+        assert_eq!(fen_after_first, "8/3k4/8/8/8/8/4P3/4K3 w - - 1 41");
+        assert_eq!(
+            res.get_prev_move(first),
+            (res.game_start(), fen.to_string())
+        );
+        assert_eq!(
+            res.get_next_move(first)[0].1,
+            "8/3k4/8/8/4P3/8/8/4K3 b - - 0 41"
+        );
+        assert!(res
+            .generate_pgn(res.game_start())
+            .starts_with(&format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n40... Kd7")));
+    }
+
+    #[test]
+    fn nags_round_trip() {
+        let tokens = tokenize("1. e4! e5?? 2. Nf3 $14 Nc6 $1 $13 3. Bb5!? *");
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+
+        let (first, _, san) = res.get_next_move(res.game_start())[0].clone();
+        assert_eq!(san, "e4!");
+        assert_eq!(res.get_move(first).unwrap().nags, vec![Nag::GOOD]);
+
+        let pgn = res.generate_pgn(res.game_start());
+        assert!(pgn.contains("Nf3 $14"));
+        assert!(pgn.contains("Nc6! $13"));
+        assert!(res.generate_move_text(res.game_start()).contains("Nc6!∞"));
+
+        let tokens = tokenize(&pgn);
+        assert_eq!(PgnParser::new(tokens.iter()).parse().unwrap(), res);
+    }
+
+    #[test]
+    fn comments_round_trip() {
+        let tokens = tokenize(
+            "{Opening comment} 1. e4 {Best by test} {Really} 1... e5 (1... c5 {Sicilian}) {Back to e5} 2. Nf3 ({Or} 2. f4) 2... Nc6 *",
+        );
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+        assert_eq!(res.comments(res.game_start()), ["Opening comment"]);
+
+        let (e4, _, _) = res.get_next_move(res.game_start())[0].clone();
+        assert_eq!(res.comments(e4), ["Best by test", "Really"]);
+
+        let pgn = res.generate_pgn(res.game_start());
+        assert_eq!(
+            pgn,
+            "{Opening comment} 1. e4 {Best by test} {Really} 1... e5 (1... c5 {Sicilian}) {Back to e5} 2. Nf3 ({Or} 2. f4) 2... Nc6 *"
+        );
+
+        let tokens = tokenize(&pgn);
+        assert_eq!(PgnParser::new(tokens.iter()).parse().unwrap(), res);
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let tokens = tokenize(
+            "1. e4 { [%eval 0.17] [%clk 0:03:00] } 1... e5 { Solid [%clk 0:02:58.5] [%cal Gg1f3,Rd1h5] } 2. Nf3 *",
+        );
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+
+        let (e4, _, _) = res.get_next_move(res.game_start())[0].clone();
+        let commands = &res.get_move(e4).unwrap().commands;
+        assert_eq!(commands.eval, Some(Score::Cp(17)));
+        assert_eq!(commands.clock, Some(Duration::from_secs(180)));
+        assert!(res.comments(e4).is_empty());
+
+        let (e5, _, _) = res.get_next_move(e4)[0].clone();
+        assert_eq!(res.comments(e5), ["Solid"]);
+        assert_eq!(res.get_move(e5).unwrap().commands.arrows.len(), 2);
+
+        let pgn = res.generate_pgn(res.game_start());
+        assert_eq!(
+            pgn,
+            "1. e4 {[%eval 0.17] [%clk 0:03:00]} 1... e5 {[%clk 0:02:58.5] [%cal Gg1f3,Rd1h5]} {Solid} 2. Nf3 *"
+        );
+        assert_eq!(
+            res.generate_move_text(res.game_start()),
+            "1. e4 [0:03:00] 1... e5 [0:02:58.5] {Solid} 2. Nf3 *"
+        );
+
+        let tokens = tokenize(&pgn);
+        assert_eq!(PgnParser::new(tokens.iter()).parse().unwrap(), res);
+    }
+
+    #[test]
+    fn variations_keep_their_place() {
+        let tokens = tokenize("1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3 Nc6 1-0");
+        let res = PgnParser::new(tokens.iter()).parse().unwrap();
+
+        let (e4, _, _) = res.get_next_move(res.game_start())[0].clone();
+        let replies: Vec<_> = res
+            .get_next_move(e4)
+            .into_iter()
+            .map(|(_, _, san)| san)
+            .collect();
+        assert_eq!(replies, ["e5", "c5"]);
+
+        let pgn = res.generate_pgn(res.game_start());
+        assert_eq!(
+            pgn,
+            "1. e4 e5 (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 Nc6 1-0"
+        );
+        let tokens = tokenize(&pgn);
+        assert_eq!(PgnParser::new(tokens.iter()).parse().unwrap(), res);
+    }
+
+    #[test]
+    fn rejects_invalid_fen_tag() {
+        let tokens = tokenize("[SetUp \"1\"]\n[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n*");
+        assert!(PgnParser::new(tokens.iter()).parse().is_err());
+    }
+
+    #[test]
+    fn lenient_parsing_skips_what_it_cannot_read() {
+        let tokens =
+            tokenize("1. e4 e5 2. Nf3 Nc6 ) 3. Bc4 (3. Bb5 a6 4. Ba9) 3... Nf6 & 4. d3 1-0");
+        let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 2. Nf3 Nc6 3. Bc4 (3. Bb5 a6) 3... Nf6 4. d3 1-0"
+        );
+        assert_eq!(warnings.len(), 3);
+        assert!(PgnParser::new(tokens.iter()).parse().is_err());
+    }
+
+    fn move_error(pgn: &str) -> Option<ErrorKind> {
+        let tokens = tokenize(pgn);
+        PgnParser::new(tokens.iter())
+            .parse()
+            .err()
+            .map(|error| error.kind)
+    }
+
+    #[test]
+    fn moves_are_checked_against_the_position() {
+        let error = |error, move_number, color| {
+            Some(ErrorKind::Move {
+                error,
+                move_number,
+                color,
+            })
+        };
+        assert_eq!(
+            move_error("1. e4 e5 2. Ke3 *"),
+            error(MoveError::Impossible, 2, Color::White)
+        );
+        assert_eq!(
+            move_error("1. e4 e5 2. d3 Bb4+ 3. Nc3 Nf6 4. Nd5 *"),
+            error(MoveError::Illegal, 4, Color::White)
+        );
+        assert_eq!(
+            move_error("1. e4 e5 2. Nc3 Nc6 3. Ne2 *"),
+            error(MoveError::Ambiguous, 3, Color::White)
+        );
+        assert_eq!(move_error("1. e4 e5 2. Nc3 Nc6 3. Nge2 *"), None);
+        assert_eq!(
+            move_error("1. e4 e5 2. Nf3 0-0 *"),
+            error(MoveError::Illegal, 2, Color::Black)
+        );
+
+        let tokens = tokenize("1. e4 e5 2. Nc3 Nc6 3. Ne2 *");
+        let error = PgnParser::new(tokens.iter()).parse().unwrap_err();
+        assert_eq!(error.index, 14);
+        assert_eq!(
+            error.message,
+            "Move 3. Ne2 by White can't be played: it doesn't say which piece moves."
+        );
+    }
+
+    #[test]
+    fn check_and_mate_suffixes_are_checked() {
+        let wrong_suffix = |check, checkmate| Some(MoveError::WrongSuffix { check, checkmate });
+        let suffix_error = |pgn| match move_error(pgn) {
+            Some(ErrorKind::Move { error, .. }) => Some(error),
+            _ => None,
+        };
+        assert_eq!(suffix_error("1. e4+ e5 *"), wrong_suffix(false, false));
+        assert_eq!(
+            suffix_error("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7 *"),
+            wrong_suffix(true, true)
+        );
+        assert_eq!(
+            suffix_error("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7+ *"),
+            wrong_suffix(true, true)
+        );
+        assert_eq!(
+            suffix_error("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# *"),
+            None
+        );
+
+        // Lenient parsing corrects them
+        let tokens = tokenize("1. e4+ e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7 *");
+        let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# *"
+        );
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn capture_markers_are_checked() {
+        let wrong_capture = |captures| {
+            Some(ErrorKind::Move {
+                error: MoveError::WrongCapture { captures },
+                move_number: 2,
+                color: Color::White,
+            })
+        };
+        assert_eq!(move_error("1. e4 d5 2. Nxf3 *"), wrong_capture(false));
+        assert_eq!(move_error("1. e4 d5 2. d5 *"), wrong_capture(true));
+        assert_eq!(move_error("1. e4 d5 2. exd5 *"), None);
+        assert_eq!(move_error("1. e4 a6 2. e5 d5 3. exd6 *"), None);
+
+        let tokens = tokenize("1. e4 d5 2. Nxf3 dxe4 *");
+        let error = PgnParser::new(tokens.iter()).parse().unwrap_err();
+        assert_eq!(
+            error.message,
+            "Move 2. Nxf3 by White can't be played: there is nothing to take."
+        );
+
+        // Lenient parsing corrects them
+        let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 d5 2. Nf3 dxe4 *"
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn lenient_parsing_stops_lines_at_unplayable_moves() {
+        let tokens = tokenize("1. e4 e5 (1... Ke6 2. d4) 2. Nf3 Nc6 3. Ne2 Nf6 4. d4 1-0");
+        let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 2. Nf3 Nc6 1-0"
+        );
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn other_notations_are_read_as_san() {
+        let tokens = tokenize(
+            "1. e2-e4 e7e5 2. Ng1-f3 ♞c6 3. f1c4 g8f6 4. e1g1 f8c5 5. d2-d4 Nc6xd4 6. Nf3xd4 c5xd4 *",
+        );
+        let move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. O-O Bc5 5. d4 Nxd4 6. Nxd4 Bxd4 *"
+        );
+
+        for en_passant in ["e5d6", "e5xd6", "e5-d6", "exd6"] {
+            let tokens = tokenize(&format!("1. e4 a6 2. e5 d5 3. {en_passant} *"));
+            let move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+            assert!(move_tree
+                .generate_pgn(move_tree.game_start())
+                .ends_with("3. exd6 *"));
+        }
+
+        for promotion in ["e7e8q", "e7-e8=Q", "e8=Q"] {
+            let pgn = format!(
+                "[SetUp \"1\"]\n[FEN \"8/4P1k1/8/8/8/8/8/4K3 w - - 0 1\"]\n\n1. {promotion} Kf6 *"
+            );
+            let tokens = tokenize(&pgn);
+            let move_tree = PgnParser::new(tokens.iter()).parse().unwrap();
+            assert!(move_tree
+                .generate_pgn(move_tree.game_start())
+                .ends_with("1. e8=Q Kf6 *"));
+        }
+
+        // The piece has to be the one on the source square
+        assert_eq!(
+            move_error("1. Bg1-f3 *"),
+            Some(ErrorKind::Move {
+                error: MoveError::Impossible,
+                move_number: 1,
+                color: Color::White
+            })
+        );
+    }
+
+    #[test]
+    fn parse_from_text() {
+        let pgn = "% Exported by a tool
+[SetUp \"1\"]
+[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 10\"]
+
+10. e4 ; The usual
+10... e5 11. Nf3 *";
+        let move_tree = PgnParser::from_text(pgn).parse().unwrap();
+        assert!(move_tree
+            .generate_pgn(move_tree.game_start())
+            .ends_with("10. e4 {The usual} 10... e5 11. Nf3 *"));
+    }
+
+    #[test]
+    fn lenient_parsing_keeps_unfinished_games() {
+        let tokens = tokenize("1. e4 e5 (1... c5 2. Nf3");
+        let (move_tree, warnings) = PgnParser::new(tokens.iter()).parse_lenient();
+
+        assert_eq!(
+            move_tree.generate_pgn(move_tree.game_start()),
+            "1. e4 e5 (1... c5 2. Nf3)"
+        );
+        assert_eq!(warnings.len(), 2);
+    }
+
     #[test]
     fn test_simple_game() {
         let tokens = tokenize("1.d4 1-0");
@@ -0,0 +1,143 @@
+use std::fmt::Display;
+
+use crate::common::color::Color;
+
+/// Names of the seven tags every PGN game is expected to have, in export order.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// The tag pair section of a game: the seven-tag roster plus any other tags found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    pub event: Option<String>,
+    pub site: Option<String>,
+    pub date: Option<String>,
+    pub round: Option<String>,
+    pub white: Option<String>,
+    pub black: Option<String>,
+    pub result: Option<String>,
+    extras: Vec<(String, String)>,
+}
+
+impl Tags {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "Event" => self.event.as_deref(),
+            "Site" => self.site.as_deref(),
+            "Date" => self.date.as_deref(),
+            "Round" => self.round.as_deref(),
+            "White" => self.white.as_deref(),
+            "Black" => self.black.as_deref(),
+            "Result" => self.result.as_deref(),
+            _ => self
+                .extras
+                .iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value)| value.as_str()),
+        }
+    }
+
+    /// Set a tag, replacing any earlier value with the same name.
+    pub fn set(&mut self, name: &str, value: &str) {
+        let value = value.to_string();
+        match name {
+            "Event" => self.event = Some(value),
+            "Site" => self.site = Some(value),
+            "Date" => self.date = Some(value),
+            "Round" => self.round = Some(value),
+            "White" => self.white = Some(value),
+            "Black" => self.black = Some(value),
+            "Result" => self.result = Some(value),
+            _ => match self.extras.iter_mut().find(|(tag, _)| tag == name) {
+                Some((_, old)) => *old = value,
+                None => self.extras.push((name.to_string(), value)),
+            },
+        }
+    }
+
+    /// All tags that are set, seven-tag roster first and the rest in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        SEVEN_TAG_ROSTER
+            .into_iter()
+            .filter_map(|name| self.get(name).map(|value| (name, value)))
+            .chain(
+                self.extras
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// The side `player` played, going by the `White` and `Black` tags.
+    pub fn side_of(&self, player: &str) -> Option<Color> {
+        let is_player = |name: &Option<String>| {
+            name.as_deref()
+                .is_some_and(|name| name.trim().eq_ignore_ascii_case(player.trim()))
+        };
+        if is_player(&self.white) {
+            Some(Color::White)
+        } else if is_player(&self.black) {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Tags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in self.iter() {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roster_comes_first() {
+        let mut tags = Tags::default();
+        tags.set("ECO", "C20");
+        tags.set("White", "Carlsen, Magnus");
+        tags.set("Event", "Casual game");
+        tags.set("ECO", "C21");
+
+        assert_eq!(tags.get("ECO"), Some("C21"));
+        assert_eq!(
+            tags.iter().collect::<Vec<_>>(),
+            vec![
+                ("Event", "Casual game"),
+                ("White", "Carlsen, Magnus"),
+                ("ECO", "C21")
+            ]
+        );
+    }
+
+    #[test]
+    fn side_of_player() {
+        let mut tags = Tags::default();
+        tags.set("White", "Anderssen, Adolf");
+        tags.set("Black", "Kieseritzky, Lionel");
+
+        assert_eq!(tags.side_of("kieseritzky, lionel"), Some(Color::Black));
+        assert_eq!(tags.side_of("Anderssen, Adolf"), Some(Color::White));
+        assert_eq!(tags.side_of("Morphy, Paul"), None);
+    }
+
+    #[test]
+    fn escapes_values() {
+        let mut tags = Tags::default();
+        tags.set("Annotator", r#"The "Beast" \o/"#);
+        assert_eq!(
+            tags.to_string(),
+            "[Annotator \"The \\\"Beast\\\" \\\\o/\"]\n"
+        );
+    }
+}
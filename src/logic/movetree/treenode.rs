@@ -5,13 +5,19 @@ use crate::common::file::File;
 use crate::common::piece::Piece;
 use crate::common::rank::Rank;
 
+use super::commands::Commands;
+
 pub(crate) type Notation = String;
 pub type Fen = String;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TreeNode {
-    GameStart,
-    StartVariation,
+    /// Start of the game, with the comments before its first move and the marks on the
+    /// starting position.
+    GameStart(Vec<String>, Commands),
+    /// Start of a variation, with the comments before its first move.
+    StartVariation(Vec<String>),
+    /// End of a variation, after its last move.
     EndVariation,
     Move(Fen, CMove),
     Result(CResult),
@@ -20,8 +26,13 @@ pub enum TreeNode {
 impl Display for TreeNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
-            TreeNode::GameStart => "".to_string(),
-            TreeNode::StartVariation => "( ".to_string(),
+            TreeNode::GameStart(comments, commands) if !commands.is_empty() => {
+                format!("{{{commands}}} {}", comments_to_pgn(comments))
+                    .trim_end()
+                    .to_string()
+            }
+            TreeNode::GameStart(comments, _) => comments_to_pgn(comments),
+            TreeNode::StartVariation(comments) => comments_to_pgn(comments),
             TreeNode::EndVariation => ")".to_string(),
             TreeNode::Move(_, cmove) => format!(" {} ", cmove.to_san()),
             TreeNode::Result(result) => result.to_string(),
@@ -29,6 +40,16 @@ impl Display for TreeNode {
         write!(f, "{}", str)
     }
 }
+
+/// Write comments the way PGN expects them, each between braces.
+pub fn comments_to_pgn(comments: &[String]) -> String {
+    comments
+        .iter()
+        .map(|comment| format!("{{{comment}}}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum CMoveKind {
     Regular(MoveDetails),
@@ -42,7 +63,11 @@ pub struct CMove {
     pub color: Color,
     pub checkmate: bool,
     pub move_number: usize,
-    pub comment: Option<String>,
+    pub comments_before: Vec<String>,
+    pub comments_after: Vec<String>,
+    /// Clock, evaluation and marks read from the comments after the move.
+    pub commands: Commands,
+    pub nags: Vec<Nag>,
 }
 
 impl Display for CMove {
@@ -100,14 +125,121 @@ impl CMove {
             san.push('+');
         }
 
-        // Add comment if present
-        if let Some(comment) = &self.comment {
-            san.push_str(&format!(" {}", comment));
+        san
+    }
+
+    /// SAN of the move followed by the symbols of its NAGs, for display.
+    pub fn to_annotated_san(&self) -> String {
+        self.notation(true)
+    }
+
+    /// Whether both are the same move from the same position, annotations aside.
+    pub fn is_same_move(&self, other: &CMove) -> bool {
+        self.kind == other.kind
+            && self.color == other.color
+            && self.move_number == other.move_number
+    }
+
+    /// SAN of the move followed by its NAGs, as symbols or as written in PGN.
+    pub(super) fn notation(&self, symbols: bool) -> String {
+        let mut san = self.to_san();
+        for nag in &self.nags {
+            match nag.symbol() {
+                Some(symbol) if symbols => san.push_str(symbol),
+                // Move assessments are usually written next to the move
+                Some(symbol) if nag.is_move_assessment() => san.push_str(symbol),
+                _ => san.push_str(&format!(" {nag}")),
+            }
         }
 
         san
     }
 }
+/// A Numeric Annotation Glyph, written `$n` in PGN.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Nag(pub u8);
+
+impl Nag {
+    pub const GOOD: Nag = Nag(1);
+    pub const POOR: Nag = Nag(2);
+    pub const EXCELLENT: Nag = Nag(3);
+    pub const BLUNDER: Nag = Nag(4);
+    pub const INTERESTING: Nag = Nag(5);
+    pub const DUBIOUS: Nag = Nag(6);
+    pub const DRAWISH: Nag = Nag(10);
+    pub const UNCLEAR: Nag = Nag(13);
+    pub const WHITE_SLIGHT_ADVANTAGE: Nag = Nag(14);
+    pub const BLACK_SLIGHT_ADVANTAGE: Nag = Nag(15);
+    pub const WHITE_ADVANTAGE: Nag = Nag(16);
+    pub const BLACK_ADVANTAGE: Nag = Nag(17);
+    pub const WHITE_DECISIVE_ADVANTAGE: Nag = Nag(18);
+    pub const BLACK_DECISIVE_ADVANTAGE: Nag = Nag(19);
+
+    /// Glyphs judging the move itself.
+    pub const MOVE_ASSESSMENTS: [Nag; 6] = [
+        Nag::GOOD,
+        Nag::POOR,
+        Nag::EXCELLENT,
+        Nag::BLUNDER,
+        Nag::INTERESTING,
+        Nag::DUBIOUS,
+    ];
+
+    /// Glyphs judging the position after the move.
+    pub const POSITION_ASSESSMENTS: [Nag; 8] = [
+        Nag::DRAWISH,
+        Nag::UNCLEAR,
+        Nag::WHITE_SLIGHT_ADVANTAGE,
+        Nag::BLACK_SLIGHT_ADVANTAGE,
+        Nag::WHITE_ADVANTAGE,
+        Nag::BLACK_ADVANTAGE,
+        Nag::WHITE_DECISIVE_ADVANTAGE,
+        Nag::BLACK_DECISIVE_ADVANTAGE,
+    ];
+
+    /// The symbol usually printed for the glyph, if it has one.
+    pub fn symbol(self) -> Option<&'static str> {
+        let symbol = match self.0 {
+            1 => "!",
+            2 => "?",
+            3 => "!!",
+            4 => "??",
+            5 => "!?",
+            6 => "?!",
+            10 => "=",
+            13 => "∞",
+            14 => "+=",
+            15 => "=+",
+            16 => "±",
+            17 => "∓",
+            18 => "+-",
+            19 => "-+",
+            _ => return None,
+        };
+        Some(symbol)
+    }
+
+    pub fn is_move_assessment(self) -> bool {
+        (1..=6).contains(&self.0)
+    }
+
+    pub fn is_position_assessment(self) -> bool {
+        (10..=19).contains(&self.0)
+    }
+
+    /// Whether a move can't have both glyphs, e.g. `!` and `?`.
+    pub fn excludes(self, other: Nag) -> bool {
+        (self.is_move_assessment() && other.is_move_assessment())
+            || (self.is_position_assessment() && other.is_position_assessment())
+    }
+}
+
+impl Display for Nag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "${}", self.0)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum CastleSide {
     Short,
@@ -166,7 +298,10 @@ mod tests {
             check: false,
             color: Color::White,
             checkmate: false,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
         };
         assert_eq!(cmove.to_san(), "e4");
     }
@@ -187,7 +322,10 @@ mod tests {
             check: false,
             color: Color::White,
             checkmate: false,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
         };
         assert_eq!(cmove.to_san(), "Ncd5");
     }
@@ -208,7 +346,10 @@ mod tests {
             check: true,
             color: Color::Black,
             checkmate: false,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
         };
         assert_eq!(cmove.to_san(), "Qxf7+");
     }
@@ -229,7 +370,10 @@ mod tests {
             move_number: 1,
             color: Color::White,
             checkmate: true,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
         };
         assert_eq!(cmove.to_san(), "gxh8=Q#");
     }
@@ -241,7 +385,10 @@ mod tests {
             check: false,
             color: Color::White,
             checkmate: false,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
             move_number: 1,
         };
         assert_eq!(cmove.to_san(), "O-O");
@@ -255,7 +402,10 @@ mod tests {
             check: true,
             color: Color::Black,
             checkmate: false,
-            comment: None,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![],
         };
         assert_eq!(cmove.to_san(), "O-O-O+");
     }
@@ -275,9 +425,31 @@ mod tests {
             check: false,
             color: Color::White,
             checkmate: false,
-            comment: Some("Good move!".to_string()),
+            comments_before: vec![],
+            comments_after: vec!["Good move!".to_string()],
+            commands: Commands::default(),
+            nags: vec![],
+            move_number: 1,
+        };
+        // Comments go around the move in PGN, they aren't part of its notation
+        assert_eq!(cmove.to_san(), "Bc4");
+    }
+
+    #[test]
+    fn test_move_with_nags() {
+        let cmove = CMove {
+            kind: CMoveKind::Castles(CastleSide::Short),
+            check: true,
+            color: Color::White,
+            checkmate: false,
+            comments_before: vec![],
+            comments_after: vec![],
+            commands: Commands::default(),
+            nags: vec![Nag::EXCELLENT, Nag::WHITE_ADVANTAGE, Nag(146)],
             move_number: 1,
         };
-        assert_eq!(cmove.to_san(), "Bc4 Good move!");
+        assert_eq!(cmove.to_san(), "O-O+");
+        assert_eq!(cmove.notation(false), "O-O+!! $16 $146");
+        assert_eq!(cmove.to_annotated_san(), "O-O+!!± $146");
     }
 }
@@ -1,22 +1,45 @@
-use crate::{
-    common::file::File,
-    logic::movetree::pgn::{lexer::tokenize, parser::PgnParser},
+use crate::logic::{
+    engine::analysis::{self, Analysis},
+    history::History,
+    movetree::pgn::database::{games, GameEntry},
+};
+use common::{
+    board::Board,
+    color::Color,
+    piece::{Piece, PROMOTION_PIECES},
+    r#move::Move,
+    square::Square,
+    status::GameStatus,
 };
-use common::{board::Board, r#move::Move, rank::Rank, square::Square};
 use iced::{
-    alignment, clipboard, executor, keyboard,
-    widget::{self, container, responsive, row, Button, Column, Container, Image, Row, Text},
-    Alignment, Application, Command, Element, Length, Subscription,
+    alignment, clipboard, event, executor, keyboard,
+    widget::{
+        self, canvas, checkbox, container, mouse_area, responsive, row, scrollable, text_editor,
+        Button, Column, Container, Image, Row, Text,
+    },
+    window, Alignment, Application, Command, Element, Event, Length, Subscription,
 };
 
 use logic::movetree::{MoveTree, NextMoveOptions};
 use message::Message;
 use prelude::Result;
-use std::str::FromStr;
-use views::modal::Modal;
+use std::{collections::HashSet, env, path::PathBuf, rc::Rc, str::FromStr};
+use views::{
+    analysis::{analysis_pane, eval_bar},
+    board::ChessBoard,
+    error::error_dialog,
+    games::game_list,
+    layers::Layers,
+    marks::Marks,
+    modal::Modal,
+    move_list::{move_list, move_list_id, scroll_position},
+    move_menu::move_menu,
+};
 
+mod cli;
 mod common;
 mod error;
+mod files;
 mod logic;
 mod message;
 mod prelude;
@@ -29,10 +52,228 @@ struct App {
     move_tree: MoveTree,
     displayed_node: indextree::NodeId,
     next_move_options: Option<Vec<(indextree::NodeId, String)>>,
+    /// A pawn move to the last rank, waiting for the user to pick a piece.
+    pending_promotion: Option<(Move, indextree::NodeId)>,
+    /// Status of the game at `displayed_node`.
+    status: GameStatus,
+    /// The move that reached `displayed_node`.
+    last_move: Option<Move>,
+    games: Rc<Vec<GameEntry>>,
+    current_game: Option<usize>,
+    current_file: Option<PathBuf>,
+    /// Whether the game has changes that aren't saved to `current_file` yet.
+    dirty: bool,
+    /// Whether unsaved changes were thrown away to load something, which undo brings back.
+    discarded: bool,
+    history: History<Snapshot>,
+    /// The node whose comment is being typed, the whole text being undone at once.
+    typing_comment: Option<indextree::NodeId>,
+    analysis: Analysis,
+    engine_program: String,
+    /// Name of the user, to show their games from their side.
+    player: Option<String>,
+    /// Whether Black is at the bottom of the board.
+    flipped: bool,
+    /// Whether the board shows file and rank labels.
+    coordinates: bool,
+    /// Whether the menu to annotate the displayed move and edit its variations is open.
+    move_menu: bool,
+    /// Comments of `comment_node`, as shown in the comment editor.
+    comment: text_editor::Content,
+    comment_node: indextree::NodeId,
+    /// Whether the comment editor holds the comments before the move rather than after it.
+    comments_before: bool,
+    /// Variations folded in the move list.
+    collapsed: HashSet<indextree::NodeId>,
+    /// What failed and why, shown until dismissed.
+    error: Option<(&'static str, String)>,
+}
+
+/// The game and database as they were before an edit, to undo it.
+struct Snapshot {
+    move_tree: MoveTree,
+    displayed_node: indextree::NodeId,
+    games: Rc<Vec<GameEntry>>,
+    current_game: Option<usize>,
+    current_file: Option<PathBuf>,
+    dirty: bool,
 }
 
 fn main() -> iced::Result {
-    App::run(iced::Settings::default())
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "perft") {
+        if let Err(e) = cli::perft(&args[1..]) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    App::run(iced::Settings {
+        window: window::Settings {
+            // Closing is handled in `update`, to not lose unsaved changes
+            exit_on_close_request: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+fn piece_image(piece: Piece, color: Color) -> String {
+    let piece_color = match color {
+        Color::White => "white",
+        Color::Black => "black",
+    };
+    let piece_type = match piece {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+    };
+    format!("resources/images/pieces/{piece_color}_{piece_type}.png")
+}
+
+impl App {
+    fn load_move_tree(&mut self, move_tree: MoveTree) {
+        self.analysis.new_game();
+        self.board =
+            Board::from_str(move_tree.root_fen()).expect("Failed to load board from root fen");
+        self.displayed_node = move_tree.game_start();
+        // Games of the user are seen from their side
+        self.flipped = self
+            .player
+            .as_deref()
+            .and_then(|player| move_tree.tags().side_of(player))
+            == Some(Color::Black);
+        self.move_tree = move_tree;
+        self.selected_square = None;
+        self.next_move_options = None;
+        self.dirty = false;
+        self.collapsed.clear();
+        self.load_comment();
+    }
+
+    /// Show the comments of the displayed node in the comment editor.
+    fn load_comment(&mut self) {
+        self.comment_node = self.displayed_node;
+        let comments = if self.editing_comments_before() {
+            self.move_tree.comments_before(self.comment_node)
+        } else {
+            self.move_tree.comments(self.comment_node)
+        };
+        self.comment = text_editor::Content::with_text(&comments.join("\n\n"));
+        self.typing_comment = None;
+    }
+
+    /// Whether the comment editor holds the comments before the move, starts of the game and
+    /// variations only having comments after them.
+    fn editing_comments_before(&self) -> bool {
+        self.comments_before && self.move_tree.get_move(self.comment_node).is_some()
+    }
+
+    fn go_to(&mut self, node: indextree::NodeId) {
+        if let Some(fen) = self.move_tree.get_fen_for_node(node) {
+            self.board = Board::from_str(fen).expect("Failed to load board from next_move fen");
+        };
+        self.next_move_options = None;
+        self.displayed_node = node;
+    }
+
+    /// Apply `edit` to the tree at the displayed node.
+    ///
+    /// When the displayed move is deleted, the board goes back to the last position left.
+    fn edit_move_tree(&mut self, edit: impl FnOnce(&mut MoveTree, indextree::NodeId) -> bool) {
+        self.move_menu = false;
+        let path = self.move_tree.path(self.displayed_node);
+        if !edit(&mut self.move_tree, self.displayed_node) {
+            return;
+        }
+        self.dirty = true;
+        if let Some(node) = path.into_iter().find(|node| self.move_tree.contains(*node)) {
+            self.go_to(node);
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            move_tree: self.move_tree.clone(),
+            displayed_node: self.displayed_node,
+            games: Rc::clone(&self.games),
+            current_game: self.current_game,
+            current_file: self.current_file.clone(),
+            dirty: self.dirty || self.discarded,
+        }
+    }
+
+    /// Remember the current state to undo what comes next.
+    fn record(&mut self) {
+        self.history.record(self.snapshot());
+        self.discarded = false;
+        self.typing_comment = None;
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.move_tree = snapshot.move_tree;
+        self.games = snapshot.games;
+        self.current_game = snapshot.current_game;
+        self.current_file = snapshot.current_file;
+        self.dirty = snapshot.dirty;
+        self.go_to(snapshot.displayed_node);
+        self.selected_square = None;
+        self.pending_promotion = None;
+        self.move_menu = false;
+        self.load_comment();
+    }
+
+    /// Show the game at `index` in the database, with what had to be skipped to read it.
+    fn open_game(&mut self, index: usize) {
+        let Some(entry) = self.games.get(index) else {
+            return;
+        };
+        if let Ok(move_tree) = &entry.game {
+            let move_tree = move_tree.clone();
+            let warnings: Vec<_> = entry.warnings.iter().map(ToString::to_string).collect();
+            self.load_move_tree(move_tree);
+            self.current_game = Some(index);
+            if !warnings.is_empty() {
+                self.error = Some(("Parts of the game were skipped", warnings.join("\n\n")));
+            }
+        }
+    }
+
+    /// Record the result when `node` ends the game.
+    fn add_result_if_over(&mut self, node: indextree::NodeId) {
+        if let Some(result) = self.move_tree.status(node).result() {
+            self.move_tree.add_result(result, node);
+        }
+    }
+
+    /// Ask before `message` throws away unsaved changes.
+    fn confirm_discard(&self, message: Message) -> Command<Message> {
+        Command::perform(files::confirm_discard(), move |discard| {
+            Message::Discard(discard.then(|| Box::new(message)))
+        })
+    }
+
+    /// The whole database with the current game as it is now.
+    fn pgn(&self) -> String {
+        let current = self.move_tree.generate_pgn(self.move_tree.game_start());
+        if self.current_game.is_none() {
+            return current;
+        }
+        let games: Vec<&str> = self
+            .games
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| match self.current_game {
+                Some(current_game) if current_game == index => current.trim(),
+                _ => entry.text.trim(),
+            })
+            .collect();
+        games.join("\n\n")
+    }
 }
 
 impl Application for App {
@@ -50,19 +291,83 @@ impl Application for App {
             move_tree,
             displayed_node,
             next_move_options: None,
+            pending_promotion: None,
+            status: GameStatus::Ongoing,
+            last_move: None,
+            games: Rc::default(),
+            current_game: None,
+            current_file: None,
+            dirty: false,
+            discarded: false,
+            history: History::default(),
+            typing_comment: None,
+            analysis: Analysis::default(),
+            engine_program: env::var("ACE_CHESS_ENGINE").unwrap_or_else(|_| "stockfish".into()),
+            player: env::var("ACE_CHESS_PLAYER").ok(),
+            flipped: false,
+            coordinates: true,
+            move_menu: false,
+            comment: text_editor::Content::new(),
+            comment_node: displayed_node,
+            comments_before: false,
+            collapsed: HashSet::new(),
+            error: None,
         };
         (app, Command::none())
     }
 
     fn title(&self) -> String {
-        String::from("Ace Chess")
+        match &self.current_file {
+            Some(path) => format!(
+                "Ace Chess - {}{}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                if self.dirty { "*" } else { "" }
+            ),
+            None => String::from("Ace Chess"),
+        }
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        let displayed_before = self.displayed_node;
+        // Edits of the tree can be undone, typing a comment as a whole
+        let typing = matches!(message, Message::EditComment(_));
+        let snapshot = match &message {
+            Message::EditComment(action) => (action.is_edit()
+                && self.typing_comment != Some(self.comment_node))
+            .then(|| self.snapshot()),
+            message if message.edits_move_tree() => Some(self.snapshot()),
+            _ => None,
+        };
         match message {
+            // Nothing is lost by looking at why a game couldn't be read
+            Message::OpenGame(index)
+                if self
+                    .games
+                    .get(index)
+                    .is_some_and(|entry| entry.game.is_err()) =>
+            {
+                if let Some(Err(error)) = self.games.get(index).map(|entry| &entry.game) {
+                    self.error = Some(("Could not read the game", error.to_string()));
+                }
+            }
+            Message::InitLoadPgn
+            | Message::InitLoadFen
+            | Message::Open
+            | Message::OpenGame(_)
+            | Message::CloseRequested
+                if self.dirty =>
+            {
+                return self.confirm_discard(message)
+            }
             Message::SelectSquare(square) => self.selected_square = Some(square),
             Message::MakeMove(attempted_move, displayed_node) => {
-                if self.board.is_legal(attempted_move) {
+                if self.board.is_promotion(attempted_move)
+                    && attempted_move.promotion.is_none()
+                    && self.board.is_legal(attempted_move)
+                {
+                    self.pending_promotion = Some((attempted_move, displayed_node));
+                    self.selected_square = None;
+                } else if self.board.is_legal(attempted_move) {
                     if let Ok(cmove) = attempted_move.try_into_cmove(self.board) {
                         self.board = self.board.update(attempted_move);
                         let new_node = self.move_tree.add_new_move(
@@ -70,8 +375,10 @@ impl Application for App {
                             displayed_node,
                             self.board.to_string(),
                         );
+                        self.add_result_if_over(new_node);
                         self.selected_square = None;
                         self.displayed_node = new_node;
+                        self.dirty = true;
                     } else {
                         self.selected_square = None;
                     }
@@ -86,6 +393,12 @@ impl Application for App {
                     self.selected_square = None;
                 }
             }
+            Message::Promote(piece) => {
+                if let Some((pawn_move, node)) = self.pending_promotion.take() {
+                    return self.update(Message::MakeMove(pawn_move.with_promotion(piece), node));
+                }
+            }
+            Message::CancelPromotion => self.pending_promotion = None,
             Message::GoPrevMove => {
                 let (id, fen) = self.move_tree.get_prev_move(self.displayed_node);
                 self.board =
@@ -106,14 +419,7 @@ impl Application for App {
                     Err(_) => eprintln!("Could not get next move"),
                 }
             }
-            Message::GoToNode(id) => {
-                if let Some(fen) = self.move_tree.get_fen_for_node(id) {
-                    self.board =
-                        Board::from_str(fen).expect("Failed to load board from next_move fen");
-                };
-                self.next_move_options = None;
-                self.displayed_node = id;
-            }
+            Message::GoToNode(id) => self.go_to(id),
             Message::InitLoadPgn => {
                 return clipboard::read(|content| {
                     if let Some(content) = content {
@@ -124,102 +430,328 @@ impl Application for App {
                 })
             }
             Message::LoadPgn(pgn) => {
-                let tokens = tokenize(&pgn);
-                if let Ok(parsed) = PgnParser::new(tokens.iter()).parse() {
-                    self.move_tree = parsed.clone();
+                self.record();
+                self.current_file = None;
+                self.current_game = None;
+                // Games are read up to the first one that could be parsed, which is opened
+                let mut games = games(&pgn);
+                let mut entries = vec![];
+                for entry in games.by_ref() {
+                    let parsed = entry.game.is_ok();
+                    entries.push(entry);
+                    if parsed {
+                        break;
+                    }
+                }
+                self.games = Rc::new(entries);
+                if self.games.last().is_some_and(|entry| entry.game.is_ok()) {
+                    self.open_game(self.games.len() - 1);
+                } else if let Some(Err(error)) = self.games.first().map(|entry| &entry.game) {
+                    self.error = Some(("Could not read the PGN", error.to_string()));
+                }
+                // The rest are only in the game list
+                Rc::make_mut(&mut self.games).extend(games);
+            }
+            Message::OpenGame(index) => {
+                self.record();
+                self.open_game(index);
+            }
+            Message::InitLoadFen => {
+                return clipboard::read(|content| Message::LoadFen(content.unwrap_or_default()))
+            }
+            Message::LoadFen(fen) => match MoveTree::with_root_fen(&fen) {
+                Ok(move_tree) => {
+                    self.record();
+                    self.load_move_tree(move_tree);
+                    self.current_game = None;
+                    self.current_file = None;
+                }
+                Err(_) => {
+                    self.error = Some((
+                        "Could not load the position",
+                        format!("Not a valid FEN: {}", fen.trim()),
+                    ))
                 }
+            },
+            Message::ToggleEngine => self.analysis.toggle(),
+            Message::RestartEngine => self.analysis.restart(),
+            Message::SetSearchLimit(limit) => self.analysis.set_limit(limit),
+            Message::Engine(event) => self.analysis.handle(event),
+            Message::PlayPvMove(line_index, ply) => {
+                let Some(Some(line)) = self.analysis.lines().get(line_index) else {
+                    return Command::none();
+                };
+                let mut board = self.board;
+                let mut node = self.displayed_node;
+                for m in line.pv.iter().take(ply + 1) {
+                    if !board.is_legal(*m) {
+                        break;
+                    }
+                    let Ok(cmove) = m.try_into_cmove(board) else {
+                        break;
+                    };
+                    board = board.update(*m);
+                    node = self.move_tree.add_new_move(cmove, node, board.to_string());
+                }
+                self.add_result_if_over(node);
+                self.board = board;
+                self.selected_square = None;
+                self.dirty |= node != self.displayed_node;
+                self.displayed_node = node;
+            }
+            Message::Open => return Command::perform(files::open_pgn(), Message::Opened),
+            Message::Opened(Ok(Some((path, pgn)))) => {
+                let load = self.update(Message::LoadPgn(pgn));
+                self.current_file = Some(path);
+                return load;
+            }
+            Message::Opened(Ok(None)) => {}
+            Message::Opened(Err(error)) => self.error = Some(("Could not open the file", error)),
+            Message::Save => {
+                return Command::perform(
+                    files::save_pgn(self.current_file.clone(), self.pgn()),
+                    Message::Saved,
+                )
+            }
+            Message::SaveAs => {
+                return Command::perform(files::save_pgn(None, self.pgn()), Message::Saved)
             }
+            Message::Saved(Ok(Some(path))) => {
+                // Saving rewrote the game, keep the database in sync with the file
+                if let Some(entry) = self
+                    .current_game
+                    .and_then(|index| Rc::make_mut(&mut self.games).get_mut(index))
+                {
+                    entry.text = self.move_tree.generate_pgn(self.move_tree.game_start());
+                    entry.tags = self.move_tree.tags().clone();
+                    entry.game = Ok(self.move_tree.clone());
+                    entry.warnings.clear();
+                }
+                self.current_file = Some(path);
+                self.dirty = false;
+            }
+            Message::Saved(Ok(None)) => {}
+            Message::Saved(Err(error)) => self.error = Some(("Could not save the file", error)),
+            Message::Discard(Some(message)) => {
+                self.discarded = true;
+                self.dirty = false;
+                return self.update(*message);
+            }
+            Message::Discard(None) => {}
+            Message::CloseRequested => return window::close(window::Id::MAIN),
+            Message::ShowMoveMenu(node) => {
+                self.go_to(node);
+                self.move_menu = true;
+            }
+            Message::HideMoveMenu => self.move_menu = false,
+            Message::DismissError => self.error = None,
+            Message::Undo => {
+                if let Some(previous) = self.history.undo(self.snapshot()) {
+                    self.restore(previous);
+                }
+            }
+            Message::Redo => {
+                if let Some(next) = self.history.redo(self.snapshot()) {
+                    self.restore(next);
+                }
+            }
+            Message::PromoteVariation => self.edit_move_tree(MoveTree::promote_variation),
+            Message::MoveVariationUp => self.edit_move_tree(MoveTree::move_variation_up),
+            Message::MoveVariationDown => self.edit_move_tree(MoveTree::move_variation_down),
+            Message::DeleteVariation => self.edit_move_tree(MoveTree::delete_variation),
+            Message::DeleteAfter => self.edit_move_tree(MoveTree::delete_after),
+            Message::StripVariations => {
+                self.edit_move_tree(|move_tree, _| move_tree.strip_variations())
+            }
+            Message::ToggleNag(nag) => {
+                self.dirty |= self.move_tree.toggle_nag(self.displayed_node, nag);
+            }
+            Message::ToggleSquareMark(mark) => {
+                self.dirty |= self.move_tree.toggle_square_mark(self.displayed_node, mark);
+            }
+            Message::ToggleArrow(arrow) => {
+                self.dirty |= self.move_tree.toggle_arrow(self.displayed_node, arrow);
+            }
+            Message::FlipBoard => self.flipped = !self.flipped,
+            Message::ShowCoordinates(coordinates) => self.coordinates = coordinates,
+            Message::ToggleVariation(variation) => {
+                if !self.collapsed.remove(&variation) {
+                    self.collapsed.insert(variation);
+                }
+            }
+            Message::EditComment(action) => {
+                let is_edit = action.is_edit();
+                self.comment.perform(action);
+                if is_edit {
+                    // A blank line separates comments, braces would end them early
+                    let text = self.comment.text().replace('}', "");
+                    let comments = text
+                        .split("\n\n")
+                        .map(str::trim)
+                        .filter(|comment| !comment.is_empty())
+                        .map(String::from)
+                        .collect();
+                    self.dirty |= if self.editing_comments_before() {
+                        self.move_tree
+                            .set_comments_before(self.comment_node, comments)
+                    } else {
+                        self.move_tree.set_comments(self.comment_node, comments)
+                    };
+                }
+            }
+            Message::ShowCommentsBefore(before) => {
+                self.comments_before = before;
+                self.load_comment();
+            }
+        }
+        if let Some(snapshot) = snapshot {
+            if snapshot.move_tree != self.move_tree {
+                self.history.record(snapshot);
+                self.discarded = false;
+                self.typing_comment = typing.then_some(self.comment_node);
+            }
+        }
+        if self.comment_node != self.displayed_node {
+            self.load_comment();
+        }
+        self.analysis.follow(&self.board);
+        self.status = self.move_tree.status(self.displayed_node);
+        self.last_move = self.move_tree.last_move(self.displayed_node);
+        if self.displayed_node != displayed_before {
+            // Keep the displayed move in sight in the move list
+            let y = scroll_position(&self.move_tree, self.displayed_node);
+            return scrollable::snap_to(move_list_id(), scrollable::RelativeOffset { x: 0., y });
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Message, styles::Theme> {
+    fn view(&self) -> Element<'_, Message, styles::Theme> {
         let resp = responsive(move |size| {
-            let board_width = size.width * 0.75;
-            let mut board_col = Column::new().spacing(0).align_items(Alignment::Center);
-            let mut board_row = Row::new().spacing(0).align_items(Alignment::Center);
-            let ranks = (1..=8)
-                .rev()
-                .map(|r| Rank::from_str(&r.to_string()).unwrap())
-                .collect::<Vec<Rank>>();
-            let files = (1..=8)
-                .map(|f| File::from_str(&f.to_string()).unwrap())
-                .collect::<Vec<File>>();
-
-            for (i, rank) in ranks.iter().enumerate() {
-                for (j, file) in files.iter().enumerate() {
-                    let square = Square::make_square(*file, *rank);
-                    let square_content = match &self.board.on(square) {
-                        Some((piece, color)) => {
-                            let piece_color = match color {
-                                common::color::Color::White => String::from("white"),
-                                common::color::Color::Black => String::from("black"),
-                            };
-                            let piece_type = match piece {
-                                common::piece::Piece::Pawn => String::from("pawn"),
-                                common::piece::Piece::Knight => String::from("knight"),
-                                common::piece::Piece::Bishop => String::from("bishop"),
-                                common::piece::Piece::Rook => String::from("rook"),
-                                common::piece::Piece::Queen => String::from("queen"),
-                                common::piece::Piece::King => String::from("king"),
-                            };
-                            format!("{piece_color}_{piece_type}")
-                        }
-                        None => String::from(""),
-                    };
+            let board_width = size.width * 0.6;
+            let chess_board =
+                ChessBoard::new(self.board, self.selected_square, self.displayed_node)
+                    .last_move(self.last_move)
+                    .flipped(self.flipped)
+                    .coordinates(self.coordinates)
+                    .width(board_width)
+                    .height(size.height);
 
-                    let button_style = if (i + j) % 2 != 0 {
-                        if self.selected_square == Some(square) {
-                            styles::ButtonStyle::SelectedDarkSquare
-                        } else {
-                            styles::ButtonStyle::DarkSquare
-                        }
-                    } else if self.selected_square == Some(square) {
-                        styles::ButtonStyle::SelectedLightSquare
-                    } else {
-                        styles::ButtonStyle::LightSquare
-                    };
-                    board_row = board_row.push(
-                        Button::new(
-                            container(
-                                Image::new(format!("resources/images/pieces/{square_content}.png"))
-                                    .height(Length::Fill)
-                                    .width(Length::Fill),
-                            )
-                            .align_x(alignment::Horizontal::Center)
-                            .align_y(alignment::Vertical::Center),
-                        )
-                        .on_press(if let Some(selected_square) = self.selected_square {
-                            message::Message::MakeMove(
-                                Move {
-                                    to: square,
-                                    from: selected_square,
-                                },
-                                self.displayed_node,
-                            )
-                        } else {
-                            message::Message::SelectSquare(square)
-                        })
-                        .style(button_style)
-                        .width((board_width / 8.) as u16)
-                        .height((size.height / 8.) as u16), // .style(),
-                    );
-                }
-                board_col = board_col.push(board_row);
-                board_row = Row::new().spacing(0).align_items(Alignment::Center);
-            }
+            // Squares and arrows marked on the displayed position
+            let marks = match self.move_tree.commands(self.displayed_node) {
+                Some(commands) => Marks {
+                    squares: commands.squares.clone(),
+                    arrows: commands.arrows.clone(),
+                    flipped: self.flipped,
+                },
+                None => Marks {
+                    squares: vec![],
+                    arrows: vec![],
+                    flipped: self.flipped,
+                },
+            };
+            let board = Layers::new(
+                chess_board,
+                canvas(marks).width(Length::Fill).height(Length::Fill),
+            );
 
-            let move_text = row!(Text::new(
-                self.move_tree.generate_pgn(self.move_tree.game_start())
+            // Right click a move to annotate it or edit its variations, anywhere else the
+            // displayed one
+            let moves = mouse_area(move_list(
+                &self.move_tree,
+                self.displayed_node,
+                &self.collapsed,
             ))
-            .width(size.width * 0.3)
-            // .spacing(5)
-            .align_items(Alignment::End);
+            .on_right_press(Message::ShowMoveMenu(self.displayed_node));
+
+            let tags = self.move_tree.tags();
+            let players = match (&tags.white, &tags.black) {
+                (Some(white), Some(black)) => format!("{white} - {black}"),
+                _ => String::new(),
+            };
+
+            let mut side_panel = Column::new()
+                .spacing(10)
+                .width(size.width * 0.35)
+                .height(size.height);
+            if self.games.len() > 1 {
+                side_panel = side_panel.push(game_list(&self.games, self.current_game));
+            }
+            let comment = text_editor(&self.comment)
+                .on_action(Message::EditComment)
+                .height(100);
+            // Only moves have comments on both sides
+            let mut comment_header = row!(Text::new("Comment"))
+                .spacing(10)
+                .align_items(Alignment::Center);
+            if self.move_tree.get_move(self.displayed_node).is_some() {
+                comment_header = comment_header.push(
+                    checkbox("Before the move", self.comments_before)
+                        .on_toggle(Message::ShowCommentsBefore),
+                );
+            }
+
+            let board_options = row!(
+                Button::new(Text::new("Flip board"))
+                    .on_press(Message::FlipBoard)
+                    .style(styles::ButtonStyle::Normal),
+                checkbox("Coordinates", self.coordinates).on_toggle(Message::ShowCoordinates),
+                Button::new(Text::new("Undo"))
+                    .on_press_maybe(self.history.can_undo().then_some(Message::Undo))
+                    .style(styles::ButtonStyle::Normal),
+                Button::new(Text::new("Redo"))
+                    .on_press_maybe(self.history.can_redo().then_some(Message::Redo))
+                    .style(styles::ButtonStyle::Normal)
+            )
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+            let side_panel = side_panel
+                .push(board_options)
+                .push(Text::new(players))
+                .push(Text::new(self.status.to_string()))
+                .push(analysis_pane(&self.analysis, &self.board))
+                .push(moves)
+                .push(comment_header)
+                .push(comment);
 
-            let content = row!(board_col, move_text);
-            // let content = row!(board_col);
+            let content = row!(
+                board,
+                eval_bar(self.analysis.score_for_white(), size.height),
+                side_panel
+            )
+            .spacing(5);
 
-            if let Some(next_opts) = &self.next_move_options {
+            if let Some((title, details)) = &self.error {
+                let modal = container(error_dialog(title, details)).padding(10);
+                Modal::new(content, modal)
+                    .on_blur(Message::DismissError)
+                    .into()
+            } else if let Some((pawn_move, _)) = &self.pending_promotion {
+                let color = self.board.color_on(pawn_move.from).unwrap_or(Color::White);
+                let pieces = Row::new()
+                    .spacing(2)
+                    .extend(PROMOTION_PIECES.into_iter().map(|piece| {
+                        Button::new(
+                            Image::new(piece_image(piece, color))
+                                .height(Length::Fill)
+                                .width(Length::Fill),
+                        )
+                        .on_press(Message::Promote(piece))
+                        .style(styles::ButtonStyle::Normal)
+                        .width(80)
+                        .height(80)
+                        .into()
+                    }));
+                let modal = container(pieces).padding(10);
+                Modal::new(content, modal)
+                    .on_blur(Message::CancelPromotion)
+                    .into()
+            } else if self.move_menu {
+                let modal = container(move_menu(&self.move_tree, self.displayed_node)).padding(10);
+                Modal::new(content, modal)
+                    .on_blur(Message::HideMoveMenu)
+                    .into()
+            } else if let Some(next_opts) = &self.next_move_options {
                 let mut row = Row::new().spacing(2).align_items(Alignment::Center);
                 row = row.extend(next_opts.iter().map(|(node, notation)| {
                     Button::new(
@@ -234,27 +766,63 @@ impl Application for App {
                     .into()
                 }));
                 let modal = container(row).width(300).padding(10);
-                return Modal::new(content, modal).into();
+                Modal::new(content, modal).into()
             } else {
                 content.into()
             }
         });
-        return Container::new(resp)
+        Container::new(resp)
             .width(Length::Fill)
             .height(Length::Fill)
-            .into();
+            .into()
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        keyboard::on_key_press(|key, modifiers| match (key.as_ref(), modifiers) {
+        let keys = keyboard::on_key_press(|key, modifiers| match (key.as_ref(), modifiers) {
             (keyboard::Key::Named(keyboard::key::Named::ArrowLeft), _) => Some(Message::GoPrevMove),
             (keyboard::Key::Named(keyboard::key::Named::ArrowRight), _) => {
                 Some(Message::GoNextMove)
             }
+            (keyboard::Key::Character("v" | "V"), modifier)
+                if modifier.command() && modifier.shift() =>
+            {
+                Some(Message::InitLoadFen)
+            }
             (keyboard::Key::Character("v"), modifier) if modifier.command() => {
                 Some(Message::InitLoadPgn)
             }
+            (keyboard::Key::Character("o"), modifier) if modifier.command() => Some(Message::Open),
+            (keyboard::Key::Character("z" | "Z"), modifier)
+                if modifier.command() && modifier.shift() =>
+            {
+                Some(Message::Redo)
+            }
+            (keyboard::Key::Character("z"), modifier) if modifier.command() => Some(Message::Undo),
+            (keyboard::Key::Character("f"), modifier) if modifier.is_empty() => {
+                Some(Message::FlipBoard)
+            }
+            (keyboard::Key::Character("s" | "S"), modifier)
+                if modifier.command() && modifier.shift() =>
+            {
+                Some(Message::SaveAs)
+            }
+            (keyboard::Key::Character("s"), modifier) if modifier.command() => Some(Message::Save),
             _ => None,
-        })
+        });
+
+        let close = event::listen_with(|event, _| match event {
+            Event::Window(_, window::Event::CloseRequested) => Some(Message::CloseRequested),
+            _ => None,
+        });
+
+        if self.analysis.is_enabled() {
+            Subscription::batch([
+                keys,
+                close,
+                analysis::subscription(self.engine_program.clone()).map(Message::Engine),
+            ])
+        } else {
+            Subscription::batch([keys, close])
+        }
     }
 }
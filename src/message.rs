@@ -1,14 +1,86 @@
-use crate::common::{r#move::Move, square::Square};
+use crate::{
+    common::{piece::Piece, r#move::Move, square::Square},
+    logic::{
+        engine::{analysis, SearchLimit},
+        movetree::{
+            commands::{Arrow, SquareMark},
+            treenode::Nag,
+        },
+    },
+};
+use iced::widget::text_editor;
 use indextree::NodeId;
+use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
 pub enum Message {
     SelectSquare(Square),
     MakeMove(Move, NodeId),
+    Promote(Piece),
+    CancelPromotion,
     // HideNextMoveOptions,
     GoPrevMove,
     GoNextMove,
     GoToNode(NodeId),
     InitLoadPgn,
     LoadPgn(String),
+    InitLoadFen,
+    LoadFen(String),
+    OpenGame(usize),
+    Open,
+    /// The file that was opened and its content, if one was picked.
+    Opened(Result<Option<(PathBuf, String)>, String>),
+    Save,
+    SaveAs,
+    /// Where the game was saved, if a file was picked.
+    Saved(Result<Option<PathBuf>, String>),
+    /// Carry on with the message if the user agreed to lose their unsaved changes.
+    Discard(Option<Box<Message>>),
+    CloseRequested,
+    ToggleEngine,
+    RestartEngine,
+    SetSearchLimit(SearchLimit),
+    Engine(analysis::Event),
+    PlayPvMove(usize, usize),
+    /// Go to a move and open the menu to annotate it and edit its variations.
+    ShowMoveMenu(NodeId),
+    HideMoveMenu,
+    ToggleNag(Nag),
+    EditComment(text_editor::Action),
+    /// Edit the comments before the displayed move instead of those after it.
+    ShowCommentsBefore(bool),
+    ToggleSquareMark(SquareMark),
+    ToggleArrow(Arrow),
+    FlipBoard,
+    ShowCoordinates(bool),
+    ToggleVariation(NodeId),
+    PromoteVariation,
+    MoveVariationUp,
+    MoveVariationDown,
+    DeleteVariation,
+    DeleteAfter,
+    StripVariations,
+    Undo,
+    Redo,
+    DismissError,
+}
+
+impl Message {
+    /// Whether the message can change the moves, comments or annotations of the game.
+    pub fn edits_move_tree(&self) -> bool {
+        matches!(
+            self,
+            Message::MakeMove(..)
+                | Message::PlayPvMove(..)
+                | Message::ToggleNag(_)
+                | Message::ToggleSquareMark(_)
+                | Message::ToggleArrow(_)
+                | Message::PromoteVariation
+                | Message::MoveVariationUp
+                | Message::MoveVariationDown
+                | Message::DeleteVariation
+                | Message::DeleteAfter
+                | Message::StripVariations
+        )
+    }
 }
@@ -1,8 +1,11 @@
 use iced::overlay::menu;
-use iced::theme::{Checkbox, Container, Menu, PickList, Radio, Scrollable, Slider, Svg, TextInput};
+use iced::theme::{
+    Checkbox, Menu, PickList, Radio, Scrollable, Slider, Svg, TextEditor, TextInput,
+};
 use iced::widget::slider::{Handle, HandleShape};
 use iced::widget::{
-    button, checkbox, container, pick_list, radio, scrollable, slider, svg, text, text_input,
+    button, checkbox, container, pick_list, radio, scrollable, slider, svg, text, text_editor,
+    text_input,
 };
 use iced::{application, Border, Color, Shadow};
 
@@ -64,22 +67,6 @@ impl button::StyleSheet for Theme {
         let palette = self.palette();
 
         match style {
-            ButtonStyle::LightSquare => button::Appearance {
-                background: Some(iced::Background::Color(palette.light_square)),
-                ..Default::default()
-            },
-            ButtonStyle::DarkSquare => button::Appearance {
-                background: Some(iced::Background::Color(palette.dark_square)),
-                ..Default::default()
-            },
-            ButtonStyle::SelectedLightSquare => button::Appearance {
-                background: Some(iced::Background::Color(palette.selected_light_square)),
-                ..Default::default()
-            },
-            ButtonStyle::SelectedDarkSquare => button::Appearance {
-                background: Some(iced::Background::Color(palette.selected_dark_square)),
-                ..Default::default()
-            },
             ButtonStyle::Normal => button::Appearance {
                 border: Border {
                     radius: 0.into(),
@@ -90,11 +77,21 @@ impl button::StyleSheet for Theme {
                 text_color: palette.tab_label,
                 ..Default::default()
             },
+            ButtonStyle::Flat => button::Appearance {
+                background: Some(iced::Background::Color(Color::TRANSPARENT)),
+                text_color: palette.simple_text,
+                ..Default::default()
+            },
         }
     }
 
     fn hovered(&self, style: &Self::Style) -> button::Appearance {
         match style {
+            ButtonStyle::Flat => button::Appearance {
+                background: Some(iced::Background::Color(self.palette().dark_square)),
+                text_color: self.palette().label_selected,
+                ..Default::default()
+            },
             ButtonStyle::Normal => button::Appearance {
                 border: Border {
                     radius: 0.into(),
@@ -105,18 +102,22 @@ impl button::StyleSheet for Theme {
                 text_color: self.palette().label_selected,
                 ..Default::default()
             },
-            _ => self.active(style),
         }
     }
 }
 
 impl container::StyleSheet for Theme {
-    type Style = Container;
-
-    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+    type Style = ContainerStyle;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let background = match style {
+            ContainerStyle::Normal => Color::TRANSPARENT,
+            ContainerStyle::EvalWhite => self.palette().eval_white,
+            ContainerStyle::EvalBlack => self.palette().eval_black,
+        };
         container::Appearance {
             text_color: Some(self.palette().simple_text),
-            background: Some(iced::Background::Color(Color::TRANSPARENT)),
+            background: Some(iced::Background::Color(background)),
             border: Border {
                 radius: 2.0.into(),
                 width: 0.0,
@@ -187,6 +188,45 @@ impl text_input::StyleSheet for Theme {
     }
 }
 
+impl text_editor::StyleSheet for Theme {
+    type Style = TextEditor;
+
+    fn active(&self, _style: &Self::Style) -> text_editor::Appearance {
+        text_editor::Appearance {
+            background: iced::Background::Color(self.palette().light_square),
+            border: Border {
+                radius: 1.0.into(),
+                width: 1.,
+                color: self.palette().dark_square,
+            },
+        }
+    }
+
+    fn focused(&self, style: &Self::Style) -> text_editor::Appearance {
+        self.active(style)
+    }
+
+    fn placeholder_color(&self, _style: &Self::Style) -> Color {
+        self.palette().tab_label
+    }
+
+    fn value_color(&self, _style: &Self::Style) -> Color {
+        self.palette().tab_label
+    }
+
+    fn selection_color(&self, _style: &Self::Style) -> Color {
+        Color::WHITE
+    }
+
+    fn disabled(&self, style: &Self::Style) -> text_editor::Appearance {
+        self.active(style)
+    }
+
+    fn disabled_color(&self, _style: &Self::Style) -> Color {
+        self.palette().dark_square
+    }
+}
+
 impl svg::StyleSheet for Theme {
     type Style = Svg;
 
@@ -451,6 +491,18 @@ pub struct OCPPalette {
     pub selected_light_square: Color,
     pub selected_dark_square: Color,
     pub tab_label: Color,
+    pub eval_white: Color,
+    pub eval_black: Color,
+    pub mark_green: Color,
+    pub mark_red: Color,
+    pub mark_yellow: Color,
+    pub mark_blue: Color,
+    /// Dots and rings on the squares the selected piece can go to.
+    pub move_hint: Color,
+    /// Tint of the squares of the last move.
+    pub last_move: Color,
+    /// Tint of the square of a king in check.
+    pub check: Color,
 }
 
 impl OCPPalette {
@@ -463,6 +515,15 @@ impl OCPPalette {
         simple_text: Color::BLACK,
         label_selected: Color::WHITE,
         tab_label: Color::BLACK,
+        eval_white: rgb!(240, 240, 240),
+        eval_black: rgb!(64, 64, 64),
+        mark_green: rgb!(21, 120, 27),
+        mark_red: rgb!(136, 32, 32),
+        mark_yellow: rgb!(230, 143, 0),
+        mark_blue: rgb!(0, 48, 136),
+        move_hint: Color::from_rgba(0., 0., 0., 0.2),
+        last_move: Color::from_rgba(0.61, 0.78, 0., 0.41),
+        check: Color::from_rgba(0.9, 0.1, 0.1, 0.6),
     };
 }
 
@@ -470,8 +531,13 @@ impl OCPPalette {
 pub enum ButtonStyle {
     #[default]
     Normal,
-    LightSquare,
-    DarkSquare,
-    SelectedLightSquare,
-    SelectedDarkSquare,
+    Flat,
+}
+
+#[derive(Default)]
+pub enum ContainerStyle {
+    #[default]
+    Normal,
+    EvalWhite,
+    EvalBlack,
 }
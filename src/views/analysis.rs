@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use iced::{
+    alignment,
+    widget::{button, column, container, pick_list, row, text, Column, Row},
+    Alignment, Element, Length,
+};
+
+use crate::{
+    common::{board::Board, color::Color},
+    logic::engine::{
+        analysis::{san_line, Analysis},
+        uci::Score,
+        SearchLimit,
+    },
+    message::Message,
+    styles::{ButtonStyle, ContainerStyle, Theme},
+};
+
+/// Plies of each principal variation shown in the pane.
+const SHOWN_PLIES: usize = 12;
+
+/// How long the engine can be asked to search each position.
+const SEARCH_LIMITS: [SearchLimit; 4] = [
+    SearchLimit::Infinite,
+    SearchLimit::Depth(20),
+    SearchLimit::MoveTime(Duration::from_secs(5)),
+    SearchLimit::MoveTime(Duration::from_secs(30)),
+];
+
+/// Portion of the eval bar that White gets for a given score.
+fn white_share(score: Option<Score>) -> f32 {
+    match score {
+        None => 0.5,
+        Some(Score::Mate(moves)) if moves > 0 => 1.,
+        Some(Score::Mate(_)) => 0.,
+        // Same logistic curve as the usual centipawn to winning chances conversion
+        Some(Score::Cp(cp)) => 1. / (1. + (-0.004 * cp as f32).exp()),
+    }
+}
+
+pub fn eval_bar<'a>(score: Option<Score>, height: f32) -> Element<'a, Message, Theme> {
+    let white = (white_share(score) * 1000.) as u16;
+    column!(
+        container(text(""))
+            .width(Length::Fill)
+            .height(Length::FillPortion(1000 - white))
+            .style(ContainerStyle::EvalBlack),
+        container(text(""))
+            .width(Length::Fill)
+            .height(Length::FillPortion(white))
+            .style(ContainerStyle::EvalWhite),
+    )
+    .width(16)
+    .height(height)
+    .into()
+}
+
+pub fn analysis_pane<'a>(analysis: &Analysis, board: &Board) -> Element<'a, Message, Theme> {
+    let header = row!(
+        button(text(if analysis.is_enabled() {
+            "Stop engine"
+        } else {
+            "Start engine"
+        }))
+        .on_press(Message::ToggleEngine)
+        .style(ButtonStyle::Normal),
+        button(text("Restart"))
+            .on_press_maybe(analysis.is_enabled().then_some(Message::RestartEngine))
+            .style(ButtonStyle::Normal),
+        pick_list(
+            &SEARCH_LIMITS[..],
+            Some(analysis.limit()),
+            Message::SetSearchLimit
+        )
+        .text_size(14),
+        text(match (analysis.score_for_white(), analysis.depth()) {
+            (Some(score), Some(depth)) => match analysis.name() {
+                Some(name) => format!("{name}  {score}  depth {depth}"),
+                None => format!("{score}  depth {depth}"),
+            },
+            _ if analysis.is_enabled() => "Thinking...".to_string(),
+            _ => match analysis.error() {
+                Some(e) => format!("Engine error: {e:?}"),
+                None => String::new(),
+            },
+        })
+        .size(14),
+    )
+    .spacing(10)
+    .align_items(Alignment::Center);
+
+    let mut lines = Column::new().spacing(4);
+    for (line_index, info) in analysis.lines().iter().enumerate() {
+        let Some(info) = info else {
+            continue;
+        };
+        let score = match (info.score, board.side_to_move()) {
+            (Some(Score::Cp(cp)), Color::Black) => Some(Score::Cp(-cp)),
+            (Some(Score::Mate(moves)), Color::Black) => Some(Score::Mate(-moves)),
+            (score, _) => score,
+        };
+        let mut line = Row::new().spacing(2).align_items(Alignment::Center).push(
+            text(score.map(|s| s.to_string()).unwrap_or_default())
+                .size(14)
+                .width(50),
+        );
+
+        let mut move_number = board.fullmoves();
+        let mut side = board.side_to_move();
+        for (ply, san) in san_line(board, &info.pv)
+            .into_iter()
+            .take(SHOWN_PLIES)
+            .enumerate()
+        {
+            let label = match side {
+                Color::White => format!("{move_number}. {san}"),
+                Color::Black if ply == 0 => format!("{move_number}... {san}"),
+                Color::Black => san,
+            };
+            if side == Color::Black {
+                move_number += 1;
+            }
+            side = !side;
+
+            line = line.push(
+                button(
+                    text(label)
+                        .size(14)
+                        .horizontal_alignment(alignment::Horizontal::Center),
+                )
+                .padding([1, 3])
+                .on_press(Message::PlayPvMove(line_index, ply))
+                .style(ButtonStyle::Flat),
+            );
+        }
+        lines = lines.push(line);
+    }
+
+    container(column!(header, lines).spacing(8))
+        .width(Length::Fill)
+        .padding(5)
+        .into()
+}
@@ -0,0 +1,410 @@
+use iced::advanced::image::{self as image_renderer, FilterMethod};
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Renderer as _};
+use iced::advanced::text::{LineHeight, Renderer as _, Shaping, Text};
+use iced::advanced::widget::{self, tree, Widget};
+use iced::advanced::{Clipboard, Shell};
+use iced::widget::image;
+use iced::{
+    alignment, event, mouse, Border, Color, Element, Event, Length, Pixels, Point, Rectangle,
+    Renderer, Size,
+};
+use indextree::NodeId;
+
+use crate::{
+    common::{board::Board, piece::Piece, r#move::Move, square::Square},
+    message::Message,
+    piece_image,
+    styles::Theme,
+};
+
+/// The board of the displayed position.
+///
+/// A piece moves either by clicking it and then its destination, or by dragging it there. While
+/// a piece is picked up the squares it can go to are highlighted, and dropping it anywhere else
+/// puts it back.
+pub struct ChessBoard {
+    board: Board,
+    selected: Option<Square>,
+    /// The move that reached the position, highlighted.
+    last_move: Option<Move>,
+    /// Where moves made on the board are added in the tree.
+    node: NodeId,
+    /// Whether Black is at the bottom.
+    flipped: bool,
+    /// Whether files and ranks are written on the edge squares.
+    coordinates: bool,
+    width: f32,
+    height: f32,
+}
+
+impl ChessBoard {
+    pub fn new(board: Board, selected: Option<Square>, node: NodeId) -> Self {
+        Self {
+            board,
+            selected,
+            last_move: None,
+            node,
+            flipped: false,
+            coordinates: false,
+            width: 0.,
+            height: 0.,
+        }
+    }
+
+    pub fn last_move(self, last_move: Option<Move>) -> Self {
+        Self { last_move, ..self }
+    }
+
+    pub fn flipped(self, flipped: bool) -> Self {
+        Self { flipped, ..self }
+    }
+
+    pub fn coordinates(self, coordinates: bool) -> Self {
+        Self {
+            coordinates,
+            ..self
+        }
+    }
+
+    pub fn width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn height(self, height: f32) -> Self {
+        Self { height, ..self }
+    }
+
+    /// Whether the piece on `square` can be picked up.
+    fn can_pick(&self, square: Square) -> bool {
+        self.board.color_on_is(square, self.board.side_to_move())
+    }
+
+    /// Whether the piece on `from` takes something by going to `to`, en passant included.
+    fn is_capture(&self, from: Option<Square>, to: Square) -> bool {
+        let en_passant = self.board.en_passant() == Some(to)
+            && from.and_then(|from| self.board.piece_on(from)) == Some(Piece::Pawn);
+        self.board.on(to).is_some() || en_passant
+    }
+}
+
+/// A piece being dragged.
+#[derive(Default)]
+struct State {
+    dragging: Option<Square>,
+}
+
+fn cell(bounds: Rectangle) -> Size {
+    Size::new(bounds.width / 8., bounds.height / 8.)
+}
+
+/// The area of `square`, with White at the bottom unless the board is `flipped`.
+pub fn square_bounds(square: Square, bounds: Rectangle, flipped: bool) -> Rectangle {
+    let cell = cell(bounds);
+    let (file, rank) = (square.file().as_index(), square.rank().as_index());
+    let (column, row) = if flipped {
+        (7 - file, rank)
+    } else {
+        (file, 7 - rank)
+    };
+    Rectangle::new(
+        Point::new(
+            bounds.x + column as f32 * cell.width,
+            bounds.y + row as f32 * cell.height,
+        ),
+        cell,
+    )
+}
+
+/// The square under `cursor`, if it is over the board.
+pub fn square_at(cursor: mouse::Cursor, bounds: Rectangle, flipped: bool) -> Option<Square> {
+    let position = cursor.position_in(bounds)?;
+    let column = ((position.x / bounds.width * 8.) as usize).min(7);
+    let row = ((position.y / bounds.height * 8.) as usize).min(7);
+    let (file, rank) = if flipped {
+        (7 - column, row)
+    } else {
+        (column, 7 - row)
+    };
+    Some(Square::new(rank * 8 + file))
+}
+
+/// Largest square area centred on `center` that fits in `cell`, for a piece image.
+fn piece_bounds(center: Point, cell: Size) -> Rectangle {
+    let side = cell.width.min(cell.height);
+    Rectangle::new(
+        Point::new(center.x - side / 2., center.y - side / 2.),
+        Size::new(side, side),
+    )
+}
+
+impl ChessBoard {
+    /// Write the file on the squares of the bottom row, and the rank on the left column.
+    fn draw_coordinates(
+        &self,
+        renderer: &mut Renderer,
+        square: Square,
+        area: Rectangle,
+        theme: &Theme,
+        light: bool,
+    ) {
+        let palette = theme.palette();
+        let color = if light {
+            palette.dark_square
+        } else {
+            palette.light_square
+        };
+        let size = area.width.min(area.height) * 0.2;
+        let padding = size * 0.3;
+        let edge = if self.flipped { 7 } else { 0 };
+        let mut label = |content: &str, position, horizontal, vertical| {
+            let text = Text {
+                content,
+                bounds: area.size(),
+                size: Pixels(size),
+                line_height: LineHeight::default(),
+                font: renderer.default_font(),
+                horizontal_alignment: horizontal,
+                vertical_alignment: vertical,
+                shaping: Shaping::Basic,
+            };
+            renderer.fill_text(text, position, color, area);
+        };
+
+        let name = square.to_string();
+        if square.rank().as_index() == edge {
+            label(
+                &name[..1],
+                Point::new(
+                    area.x + area.width - padding,
+                    area.y + area.height - padding,
+                ),
+                alignment::Horizontal::Right,
+                alignment::Vertical::Bottom,
+            );
+        }
+        if square.file().as_index() == edge {
+            label(
+                &name[1..],
+                Point::new(area.x + padding, area.y + padding),
+                alignment::Horizontal::Left,
+                alignment::Vertical::Top,
+            );
+        }
+    }
+}
+
+impl Widget<Message, Theme, Renderer> for ChessBoard {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fixed(self.width), Length::Fixed(self.height))
+    }
+
+    fn layout(
+        &self,
+        _tree: &mut widget::Tree,
+        _renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        layout::Node::new(limits.resolve(
+            self.width,
+            self.height,
+            Size::new(self.width, self.height),
+        ))
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut widget::Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(square) = square_at(cursor, bounds, self.flipped) else {
+                    return event::Status::Ignored;
+                };
+                if self.can_pick(square) {
+                    state.dragging = Some(square);
+                    shell.publish(Message::SelectSquare(square));
+                } else if let Some(selected) = self.selected {
+                    shell.publish(Message::MakeMove(Move::new(selected, square), self.node));
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let Some(from) = state.dragging.take() else {
+                    return event::Status::Ignored;
+                };
+                // Releasing on the square it came from leaves the piece selected for a click
+                match square_at(cursor, bounds, self.flipped) {
+                    Some(to) if to != from => {
+                        shell.publish(Message::MakeMove(Move::new(from, to), self.node))
+                    }
+                    _ => (),
+                }
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if state.dragging.is_some() => {
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &widget::Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let state = tree.state.downcast_ref::<State>();
+        let bounds = layout.bounds();
+        let palette = theme.palette();
+        let held = state.dragging.or(self.selected);
+        let destinations = held
+            .map(|square| self.board.get_legal_moves(square))
+            .unwrap_or_default();
+        let checked_king = self
+            .board
+            .is_check()
+            .then(|| self.board.king_of(self.board.side_to_move()));
+
+        for index in 0..64 {
+            let square = Square::new(index);
+            let light = (square.file().as_index() + square.rank().as_index()) % 2 == 1;
+            let color = match (held == Some(square), light) {
+                (true, true) => palette.selected_light_square,
+                (true, false) => palette.selected_dark_square,
+                (false, true) => palette.light_square,
+                (false, false) => palette.dark_square,
+            };
+            let area = square_bounds(square, bounds, self.flipped);
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: area,
+                    ..renderer::Quad::default()
+                },
+                color,
+            );
+
+            let last_move = self
+                .last_move
+                .is_some_and(|m| m.from == square || m.to == square);
+            let tint = match (checked_king == Some(square), last_move) {
+                (true, _) => Some(palette.check),
+                (false, true) => Some(palette.last_move),
+                (false, false) => None,
+            };
+            if let Some(tint) = tint {
+                renderer.fill_quad(
+                    renderer::Quad {
+                        bounds: area,
+                        ..renderer::Quad::default()
+                    },
+                    tint,
+                );
+            }
+
+            if destinations.contains(&square) {
+                let side = area.width.min(area.height);
+                if self.is_capture(held, square) {
+                    // A ring around the piece that can be taken
+                    renderer.fill_quad(
+                        renderer::Quad {
+                            bounds: piece_bounds(area.center(), area.size()),
+                            border: Border {
+                                radius: (side / 2.).into(),
+                                width: side * 0.08,
+                                color: palette.move_hint,
+                            },
+                            ..renderer::Quad::default()
+                        },
+                        Color::TRANSPARENT,
+                    );
+                } else {
+                    let dot = side * 0.3;
+                    renderer.fill_quad(
+                        renderer::Quad {
+                            bounds: piece_bounds(area.center(), Size::new(dot, dot)),
+                            border: Border::with_radius(dot / 2.),
+                            ..renderer::Quad::default()
+                        },
+                        palette.move_hint,
+                    );
+                }
+            }
+
+            if let Some((piece, color)) = self.board.on(square) {
+                // The dragged piece is drawn under the cursor instead
+                if state.dragging != Some(square) {
+                    image_renderer::Renderer::draw(
+                        renderer,
+                        image::Handle::from_path(piece_image(piece, color)),
+                        FilterMethod::Linear,
+                        piece_bounds(area.center(), area.size()),
+                    );
+                }
+            }
+
+            if self.coordinates {
+                self.draw_coordinates(renderer, square, area, theme, light);
+            }
+        }
+
+        if let (Some(from), Some(position)) = (state.dragging, cursor.position()) {
+            if let Some((piece, color)) = self.board.on(from) {
+                image_renderer::Renderer::draw(
+                    renderer,
+                    image::Handle::from_path(piece_image(piece, color)),
+                    FilterMethod::Linear,
+                    piece_bounds(position, cell(bounds)),
+                );
+            }
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &widget::Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+        if state.dragging.is_some() {
+            return mouse::Interaction::Grabbing;
+        }
+        match square_at(cursor, layout.bounds(), self.flipped) {
+            Some(square) if self.can_pick(square) => mouse::Interaction::Grab,
+            Some(_) if self.selected.is_some() => mouse::Interaction::Pointer,
+            _ => mouse::Interaction::default(),
+        }
+    }
+}
+
+impl<'a> From<ChessBoard> for Element<'a, Message, Theme> {
+    fn from(board: ChessBoard) -> Self {
+        Element::new(board)
+    }
+}
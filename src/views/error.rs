@@ -0,0 +1,30 @@
+use iced::{
+    alignment,
+    widget::{button, column, container, text},
+    Element, Font, Length,
+};
+
+use crate::{
+    message::Message,
+    styles::{ButtonStyle, Theme},
+};
+
+/// What went wrong, with a button to get back to the game.
+///
+/// The details are in a monospace font, for the source snippets of parse errors to line up.
+pub fn error_dialog<'a>(title: &'a str, details: &'a str) -> Element<'a, Message, Theme> {
+    column!(
+        text(title).size(20),
+        text(details).font(Font::MONOSPACE).size(14),
+        container(
+            button(text("OK"))
+                .on_press(Message::DismissError)
+                .style(ButtonStyle::Normal)
+        )
+        .width(Length::Fill)
+        .align_x(alignment::Horizontal::Right)
+    )
+    .spacing(10)
+    .max_width(600)
+    .into()
+}
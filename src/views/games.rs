@@ -0,0 +1,56 @@
+use iced::{
+    widget::{button, column, scrollable, text, Column},
+    Element, Length,
+};
+
+use crate::{
+    logic::movetree::pgn::database::GameEntry,
+    message::Message,
+    styles::{ButtonStyle, Theme},
+};
+
+fn label(index: usize, entry: &GameEntry) -> String {
+    let tags = &entry.tags;
+    let white = tags.white.as_deref().unwrap_or("?");
+    let black = tags.black.as_deref().unwrap_or("?");
+    let result = tags.result.as_deref().unwrap_or("*");
+    match &entry.game {
+        Ok(_) if !entry.warnings.is_empty() => format!(
+            "{}. {white} - {black}  {result}  ({} skipped)",
+            index + 1,
+            entry.warnings.len()
+        ),
+        Ok(_) => format!("{}. {white} - {black}  {result}", index + 1),
+        Err(e) => match &e.location {
+            Some(location) => format!(
+                "{}. {white} - {black}  (error on line {})",
+                index + 1,
+                location.line
+            ),
+            None => format!("{}. {white} - {black}  ({})", index + 1, e.message),
+        },
+    }
+}
+
+/// List of the games in the loaded PGN, games that failed to parse or were partly skipped
+/// show why when clicked.
+pub fn game_list<'a>(games: &[GameEntry], current: Option<usize>) -> Element<'a, Message, Theme> {
+    let entries = games.iter().enumerate().map(|(index, entry)| {
+        let entry_button = button(text(label(index, entry)).size(14))
+            .width(Length::Fill)
+            .padding([2, 5])
+            .style(if current == Some(index) {
+                ButtonStyle::Normal
+            } else {
+                ButtonStyle::Flat
+            });
+        entry_button.on_press(Message::OpenGame(index)).into()
+    });
+
+    column!(
+        text(format!("Games ({})", games.len())),
+        scrollable(Column::with_children(entries).spacing(2)).height(200)
+    )
+    .spacing(5)
+    .into()
+}
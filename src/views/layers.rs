@@ -0,0 +1,210 @@
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::overlay;
+use iced::advanced::renderer;
+use iced::advanced::widget::{self, Widget};
+use iced::advanced::{self, Clipboard, Shell};
+use iced::event;
+use iced::mouse;
+use iced::{Element, Event, Length, Rectangle, Size, Vector};
+
+/// A widget that draws an element over a base element of the same size.
+///
+/// Events go to the top element first, and to the base when it ignores them.
+pub struct Layers<'a, Message, Theme, Renderer> {
+    base: Element<'a, Message, Theme, Renderer>,
+    top: Element<'a, Message, Theme, Renderer>,
+}
+
+impl<'a, Message, Theme, Renderer> Layers<'a, Message, Theme, Renderer> {
+    /// Returns new [`Layers`]
+    pub fn new(
+        base: impl Into<Element<'a, Message, Theme, Renderer>>,
+        top: impl Into<Element<'a, Message, Theme, Renderer>>,
+    ) -> Self {
+        Self {
+            base: base.into(),
+            top: top.into(),
+        }
+    }
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for Layers<'a, Message, Theme, Renderer>
+where
+    Renderer: advanced::Renderer,
+{
+    fn children(&self) -> Vec<widget::Tree> {
+        vec![widget::Tree::new(&self.base), widget::Tree::new(&self.top)]
+    }
+
+    fn diff(&self, tree: &mut widget::Tree) {
+        tree.diff_children(&[&self.base, &self.top]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.base.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut widget::Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let base = self
+            .base
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits);
+        let size = base.size();
+        let top = self.top.as_widget().layout(
+            &mut tree.children[1],
+            renderer,
+            &layout::Limits::new(size, size),
+        );
+        layout::Node::with_children(size, vec![base, top])
+    }
+
+    fn on_event(
+        &mut self,
+        state: &mut widget::Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let mut children = layout.children();
+        let (base, top) = (children.next().unwrap(), children.next().unwrap());
+        let status = self.top.as_widget_mut().on_event(
+            &mut state.children[1],
+            event.clone(),
+            top,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        );
+        if status == event::Status::Captured {
+            return status;
+        }
+        self.base.as_widget_mut().on_event(
+            &mut state.children[0],
+            event,
+            base,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn draw(
+        &self,
+        state: &widget::Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let mut children = layout.children();
+        let (base, top) = (children.next().unwrap(), children.next().unwrap());
+        self.base.as_widget().draw(
+            &state.children[0],
+            renderer,
+            theme,
+            style,
+            base,
+            cursor,
+            viewport,
+        );
+        // Keep the top element over everything the base drew
+        renderer.with_layer(layout.bounds(), |renderer| {
+            self.top.as_widget().draw(
+                &state.children[1],
+                renderer,
+                theme,
+                style,
+                top,
+                cursor,
+                viewport,
+            );
+        });
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        state: &'b mut widget::Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.base.as_widget_mut().overlay(
+            &mut state.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            translation,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &widget::Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let mut children = layout.children();
+        let (base, top) = (children.next().unwrap(), children.next().unwrap());
+        let top = self.top.as_widget().mouse_interaction(
+            &state.children[1],
+            top,
+            cursor,
+            viewport,
+            renderer,
+        );
+        if top != mouse::Interaction::default() {
+            return top;
+        }
+        self.base.as_widget().mouse_interaction(
+            &state.children[0],
+            base,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn operate(
+        &self,
+        state: &mut widget::Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        self.base.as_widget().operate(
+            &mut state.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            operation,
+        );
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<Layers<'a, Message, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Theme: 'a,
+    Message: 'a,
+    Renderer: 'a + advanced::Renderer,
+{
+    fn from(layers: Layers<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(layers)
+    }
+}
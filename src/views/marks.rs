@@ -0,0 +1,169 @@
+use iced::{
+    keyboard, mouse,
+    widget::canvas::{self, event, Frame, Geometry, Path, Stroke},
+    Color, Point, Rectangle, Renderer, Vector,
+};
+
+use crate::{
+    common::square::Square,
+    logic::movetree::commands::{Arrow, MarkColor, SquareMark},
+    message::Message,
+    styles::Theme,
+};
+
+use super::board::{square_at, square_bounds};
+
+/// Coloured squares and arrows, drawn over the board.
+///
+/// Right clicking a square colours it, dragging with the right button draws an arrow. Holding
+/// Shift, Alt or both picks another colour than green.
+pub struct Marks {
+    pub squares: Vec<SquareMark>,
+    pub arrows: Vec<Arrow>,
+    pub flipped: bool,
+}
+
+#[derive(Default)]
+pub struct Drawing {
+    /// Where the right button was pressed.
+    start: Option<Square>,
+    modifiers: keyboard::Modifiers,
+}
+
+impl Drawing {
+    fn color(&self) -> MarkColor {
+        match (self.modifiers.shift(), self.modifiers.alt()) {
+            (false, false) => MarkColor::Green,
+            (true, false) => MarkColor::Red,
+            (false, true) => MarkColor::Blue,
+            (true, true) => MarkColor::Yellow,
+        }
+    }
+}
+
+fn mark_color(theme: &Theme, color: MarkColor) -> Color {
+    let palette = theme.palette();
+    match color {
+        MarkColor::Green => palette.mark_green,
+        MarkColor::Red => palette.mark_red,
+        MarkColor::Yellow => palette.mark_yellow,
+        MarkColor::Blue => palette.mark_blue,
+    }
+}
+
+fn draw_arrow(frame: &mut Frame, from: Point, to: Point, color: Color, thickness: f32) {
+    let length = from.distance(to);
+    if length == 0. {
+        return;
+    }
+    // Unit vectors along the arrow and across it
+    let along = Vector::new((to.x - from.x) / length, (to.y - from.y) / length);
+    let across = Vector::new(-along.y, along.x);
+    let head = thickness * 0.4;
+    let base = to - along * head;
+
+    frame.stroke(
+        &Path::line(from, base),
+        Stroke::default()
+            .with_color(color)
+            .with_width(thickness * 0.15),
+    );
+    frame.fill(
+        &Path::new(|path| {
+            path.move_to(to);
+            path.line_to(base + across * (head * 0.6));
+            path.line_to(base - across * (head * 0.6));
+            path.close();
+        }),
+        color,
+    );
+}
+
+impl canvas::Program<Message, Theme> for Marks {
+    type State = Drawing;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        match event {
+            canvas::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = modifiers;
+                (event::Status::Ignored, None)
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                state.start = square_at(cursor, bounds, self.flipped);
+                match state.start {
+                    Some(_) => (event::Status::Captured, None),
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Right)) => {
+                let Some(from) = state.start.take() else {
+                    return (event::Status::Ignored, None);
+                };
+                let color = state.color();
+                let message = square_at(cursor, bounds, self.flipped).map(|to| {
+                    if to == from {
+                        Message::ToggleSquareMark(SquareMark { color, square: to })
+                    } else {
+                        Message::ToggleArrow(Arrow { color, from, to })
+                    }
+                });
+                (event::Status::Captured, message)
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        // The frame is drawn from its own top left corner
+        let area =
+            |square| square_bounds(square, Rectangle::with_size(bounds.size()), self.flipped);
+        let center = |square| area(square).center();
+        let thickness = (bounds.width / 8.).min(bounds.height / 8.);
+
+        for mark in &self.squares {
+            let square = area(mark.square);
+            frame.fill_rectangle(
+                square.position(),
+                square.size(),
+                Color {
+                    a: 0.5,
+                    ..mark_color(theme, mark.color)
+                },
+            );
+        }
+
+        for arrow in &self.arrows {
+            let color = Color {
+                a: 0.8,
+                ..mark_color(theme, arrow.color)
+            };
+            let (from, to) = (center(arrow.from), center(arrow.to));
+            draw_arrow(&mut frame, from, to, color, thickness);
+        }
+
+        // The arrow being drawn
+        if let (Some(from), Some(to)) = (state.start, square_at(cursor, bounds, self.flipped)) {
+            let color = Color {
+                a: 0.5,
+                ..mark_color(theme, state.color())
+            };
+            draw_arrow(&mut frame, center(from), center(to), color, thickness);
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -1,3 +1,13 @@
+pub mod analysis;
+pub mod board;
+pub mod error;
+pub mod games;
+pub mod layers;
+pub mod marks;
+pub mod move_list;
+pub mod move_menu;
+pub mod nags;
+
 pub mod modal {
     use iced::advanced::layout::{self, Layout};
     use iced::advanced::overlay;
@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+
+use iced::{
+    widget::{button, container, mouse_area, row, scrollable, text, Column, Row},
+    Alignment, Element, Length,
+};
+use indextree::NodeId;
+
+use crate::{
+    common::color::Color,
+    logic::movetree::{commands::format_clock, treenode::CMove, MoveTree},
+    message::Message,
+    styles::{ButtonStyle, Theme},
+};
+
+const TEXT_SIZE: u16 = 14;
+const CLOCK_SIZE: u16 = 11;
+/// Moves on a line of a variation before it wraps.
+const MOVES_PER_LINE: usize = 6;
+/// How far a variation is moved right of the line it branches from.
+const INDENT: u16 = 16;
+
+/// Id of the scrollable holding the move list, to scroll it to the displayed move.
+pub fn move_list_id() -> scrollable::Id {
+    scrollable::Id::new("move-list")
+}
+
+struct MoveList<'a> {
+    move_tree: &'a MoveTree,
+    displayed_node: NodeId,
+    collapsed: &'a HashSet<NodeId>,
+}
+
+impl<'a> MoveList<'a> {
+    fn move_button(&self, node: NodeId, label: String) -> Element<'a, Message, Theme> {
+        let button = button(text(label).size(TEXT_SIZE))
+            .padding([2, 5])
+            .on_press(Message::GoToNode(node))
+            .style(if node == self.displayed_node {
+                ButtonStyle::Normal
+            } else {
+                ButtonStyle::Flat
+            });
+        let button = mouse_area(button).on_right_press(Message::ShowMoveMenu(node));
+        // The time left on the clock of the player after the move
+        match self
+            .move_tree
+            .commands(node)
+            .and_then(|commands| commands.clock)
+        {
+            Some(clock) => row!(button, text(format_clock(clock)).size(CLOCK_SIZE))
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+            None => button.into(),
+        }
+    }
+
+    fn comment(comments: &[String]) -> Option<Element<'a, Message, Theme>> {
+        (!comments.is_empty()).then(|| text(comments.join(" ")).size(TEXT_SIZE).into())
+    }
+
+    /// Whether comments or variations come between the move at `node` and the next one.
+    fn is_interrupted(&self, parent: NodeId, node: NodeId) -> bool {
+        !self.move_tree.comments(node).is_empty() || !self.move_tree.variations(parent).is_empty()
+    }
+
+    /// The comments after the move at `node`, then the variations replacing it.
+    fn interruption(
+        &self,
+        parent: NodeId,
+        node: NodeId,
+        column: &mut Vec<Element<'a, Message, Theme>>,
+    ) {
+        column.extend(Self::comment(self.move_tree.comments(node)));
+        column.extend(
+            self.move_tree
+                .variations(parent)
+                .into_iter()
+                .map(|variation| self.variation(variation)),
+        );
+    }
+
+    /// A row of the mainline, with the number of the move and White's and Black's moves.
+    fn mainline_row(
+        &self,
+        move_number: usize,
+        white: Option<NodeId>,
+        black: Option<NodeId>,
+    ) -> Element<'a, Message, Theme> {
+        let cell = |node: Option<NodeId>, empty: &str| {
+            let content = match node.zip(node.and_then(|node| self.move_tree.get_move(node))) {
+                Some((node, cmove)) => self.move_button(node, cmove.to_annotated_san()),
+                None => text(empty).size(TEXT_SIZE).into(),
+            };
+            container(content).width(Length::FillPortion(2))
+        };
+        row!(
+            text(format!("{move_number}."))
+                .size(TEXT_SIZE)
+                .width(Length::FillPortion(1)),
+            cell(white, "..."),
+            cell(black, "")
+        )
+        .into()
+    }
+
+    /// The mainline in two columns, with the variations indented under the moves they replace.
+    fn mainline(&self) -> Column<'a, Message, Theme> {
+        let start = self.move_tree.game_start();
+        let mut column: Vec<_> = Self::comment(self.move_tree.comments(start))
+            .into_iter()
+            .collect();
+        // White's move, waiting for Black's to fill its row
+        let mut white: Option<(usize, NodeId)> = None;
+        let mut node = start;
+
+        while let Some(next) = self.move_tree.mainline(node) {
+            let Some(cmove) = self.move_tree.get_move(next) else {
+                break;
+            };
+            if !cmove.comments_before.is_empty() {
+                if let Some((number, white)) = white.take() {
+                    column.push(self.mainline_row(number, Some(white), None));
+                }
+                column.extend(Self::comment(&cmove.comments_before));
+            }
+            match cmove.color {
+                Color::White => white = Some((cmove.move_number, next)),
+                Color::Black => {
+                    let white = white.take().map(|(_, white)| white);
+                    column.push(self.mainline_row(cmove.move_number, white, Some(next)));
+                }
+            }
+            if self.is_interrupted(node, next) {
+                if let Some((number, white)) = white.take() {
+                    column.push(self.mainline_row(number, Some(white), None));
+                }
+                self.interruption(node, next, &mut column);
+            }
+            node = next;
+        }
+        if let Some((number, white)) = white {
+            column.push(self.mainline_row(number, Some(white), None));
+        }
+        if let Some(result) = self.move_tree.result(node) {
+            column.push(text(result.to_string()).size(TEXT_SIZE).into());
+        }
+        Column::with_children(column).spacing(2)
+    }
+
+    /// A variation, with a button to fold it to a line of text.
+    fn variation(&self, start: NodeId) -> Element<'a, Message, Theme> {
+        let collapsed = self.collapsed.contains(&start);
+        let toggle = button(text(if collapsed { "▸" } else { "▾" }).size(TEXT_SIZE))
+            .padding([2, 5])
+            .on_press(Message::ToggleVariation(start))
+            .style(ButtonStyle::Flat);
+
+        let lines = if collapsed {
+            vec![text(self.move_tree.generate_move_text(start))
+                .size(TEXT_SIZE)
+                .into()]
+        } else {
+            self.variation_lines(start)
+        };
+        container(row!(toggle, Column::with_children(lines).spacing(2)).spacing(2))
+            .padding([0, 0, 0, INDENT])
+            .into()
+    }
+
+    /// The moves of a variation a few at a time, broken by their comments and variations.
+    fn variation_lines(&self, start: NodeId) -> Vec<Element<'a, Message, Theme>> {
+        let mut column: Vec<_> = Self::comment(self.move_tree.comments(start))
+            .into_iter()
+            .collect();
+        let mut moves = vec![];
+        // The first move and those after an interruption are numbered, as in PGN
+        let mut numbered = true;
+        let mut node = start;
+
+        while let Some(next) = self.move_tree.mainline(node) {
+            let Some(cmove) = self.move_tree.get_move(next) else {
+                break;
+            };
+            if !cmove.comments_before.is_empty() {
+                wrap(&mut moves, &mut column);
+                column.extend(Self::comment(&cmove.comments_before));
+                numbered = true;
+            }
+            moves.push(self.move_button(next, label(cmove, numbered)));
+            numbered = false;
+            if self.is_interrupted(node, next) {
+                wrap(&mut moves, &mut column);
+                self.interruption(node, next, &mut column);
+                numbered = true;
+            }
+            node = next;
+        }
+        wrap(&mut moves, &mut column);
+        column
+    }
+}
+
+fn label(cmove: &CMove, numbered: bool) -> String {
+    match (numbered, cmove.color) {
+        (_, Color::White) => format!("{}. {}", cmove.move_number, cmove.to_annotated_san()),
+        (true, Color::Black) => format!("{}... {}", cmove.move_number, cmove.to_annotated_san()),
+        (false, Color::Black) => cmove.to_annotated_san(),
+    }
+}
+
+/// Move the buttons out of `moves` into rows of `column`.
+fn wrap<'a>(
+    moves: &mut Vec<Element<'a, Message, Theme>>,
+    column: &mut Vec<Element<'a, Message, Theme>>,
+) {
+    let mut moves = std::mem::take(moves).into_iter().peekable();
+    while moves.peek().is_some() {
+        let line: Vec<_> = moves.by_ref().take(MOVES_PER_LINE).collect();
+        column.push(Row::with_children(line).spacing(2).into());
+    }
+}
+
+/// The moves after `start`, in the order they are listed.
+fn listed_moves(move_tree: &MoveTree, start: NodeId, moves: &mut Vec<NodeId>) {
+    let mut node = start;
+    while let Some(next) = move_tree.mainline(node) {
+        moves.push(next);
+        for variation in move_tree.variations(node) {
+            listed_moves(move_tree, variation, moves);
+        }
+        node = next;
+    }
+}
+
+/// How far down the list the move at `node` is, from 0 at the top to 1 at the bottom.
+pub fn scroll_position(move_tree: &MoveTree, node: NodeId) -> f32 {
+    let mut moves = vec![];
+    listed_moves(move_tree, move_tree.game_start(), &mut moves);
+    match moves.iter().position(|listed| *listed == node) {
+        Some(index) if moves.len() > 1 => index as f32 / (moves.len() - 1) as f32,
+        _ => 0.,
+    }
+}
+
+/// The moves of the game, going to a move when it is clicked.
+pub fn move_list<'a>(
+    move_tree: &'a MoveTree,
+    displayed_node: NodeId,
+    collapsed: &'a HashSet<NodeId>,
+) -> Element<'a, Message, Theme> {
+    let list = MoveList {
+        move_tree,
+        displayed_node,
+        collapsed,
+    };
+    scrollable(list.mainline().padding([0, 10, 0, 0]))
+        .id(move_list_id())
+        .height(Length::Fill)
+        .into()
+}
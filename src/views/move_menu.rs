@@ -0,0 +1,51 @@
+use iced::{
+    widget::{button, column, text, Column, Row},
+    Element,
+};
+use indextree::NodeId;
+
+use crate::{
+    logic::movetree::MoveTree,
+    message::Message,
+    styles::{ButtonStyle, Theme},
+};
+
+use super::nags::nag_menu;
+
+fn action<'a>(label: &'a str, message: Option<Message>) -> Element<'a, Message, Theme> {
+    button(text(label))
+        .on_press_maybe(message)
+        .style(ButtonStyle::Normal)
+        .into()
+}
+
+/// Glyphs of the move at `node`, and edits of the lines around it.
+///
+/// The variation edits only show up for a move in a variation.
+pub fn move_menu<'a>(move_tree: &MoveTree, node: NodeId) -> Element<'a, Message, Theme> {
+    let mut menu = Column::new().spacing(10);
+    if let Some(cmove) = move_tree.get_move(node) {
+        menu = menu.push(nag_menu(&cmove.nags));
+    }
+
+    let in_variation = move_tree.variation_of(node).is_some();
+    let variation = Row::with_children([
+        action("Promote", in_variation.then_some(Message::PromoteVariation)),
+        action("Move up", in_variation.then_some(Message::MoveVariationUp)),
+        action(
+            "Move down",
+            in_variation.then_some(Message::MoveVariationDown),
+        ),
+        action("Delete", in_variation.then_some(Message::DeleteVariation)),
+    ])
+    .spacing(5);
+    let game = Row::with_children([
+        action("Delete after this move", Some(Message::DeleteAfter)),
+        action("Delete all variations", Some(Message::StripVariations)),
+    ])
+    .spacing(5);
+
+    menu.push(column!(text("Variation"), variation).spacing(5))
+        .push(column!(text("Game"), game).spacing(5))
+        .into()
+}
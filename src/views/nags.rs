@@ -0,0 +1,36 @@
+use iced::{
+    widget::{button, column, text, Row},
+    Element,
+};
+
+use crate::{
+    logic::movetree::treenode::Nag,
+    message::Message,
+    styles::{ButtonStyle, Theme},
+};
+
+fn nag_row<'a>(nags: &[Nag], current: &[Nag]) -> Row<'a, Message, Theme> {
+    Row::with_children(nags.iter().map(|nag| {
+        button(text(nag.symbol().unwrap_or_default()))
+            .width(40)
+            .on_press(Message::ToggleNag(*nag))
+            .style(if current.contains(nag) {
+                ButtonStyle::Normal
+            } else {
+                ButtonStyle::Flat
+            })
+            .into()
+    }))
+    .spacing(2)
+}
+
+/// Glyphs that can be set on a move, the ones it already has are highlighted.
+pub fn nag_menu<'a>(current: &[Nag]) -> Element<'a, Message, Theme> {
+    column!(
+        text("Annotate move"),
+        nag_row(&Nag::MOVE_ASSESSMENTS, current),
+        nag_row(&Nag::POSITION_ASSESSMENTS, current)
+    )
+    .spacing(5)
+    .into()
+}
@@ -0,0 +1,41 @@
+#!/bin/sh
+# A scripted stand-in for a UCI engine, used by the engine tests.
+#
+# It answers the handshake, echoes back the position it was given as an
+# `info string`, and plays out a fixed search: `go infinite` keeps "thinking"
+# until `stop`, any other `go` finishes straight away.
+
+position=""
+
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Fake Engine"
+            echo "id author ace-chess"
+            echo "option name MultiPV type spin default 1 min 1 max 500"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        position*)
+            position="$line"
+            ;;
+        "go infinite")
+            echo "info string $position"
+            echo "info depth 1 seldepth 1 multipv 1 score cp 25 nodes 20 nps 20000 time 1 pv e2e4"
+            ;;
+        go*)
+            echo "info string $position"
+            echo "info depth 1 seldepth 1 multipv 1 score cp 25 nodes 20 nps 20000 time 1 pv e2e4"
+            echo "info depth 2 seldepth 3 multipv 1 score mate 3 nodes 80 nps 40000 time 2 pv f1c4 g8f6"
+            echo "bestmove f1c4 ponder g8f6"
+            ;;
+        stop)
+            echo "bestmove e2e4"
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done